@@ -1,5 +1,9 @@
 //! Main controls for the CLI.
+use crate::search::config::{
+    Config, DepLinks, LinkPolicy, MdLinks, Mentions, PrivateLinks, SelfLinks,
+};
 use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -12,6 +16,63 @@ pub struct Cli {
     #[arg(short, long, default_value_t = false)]
     pub debug: bool,
 
+    /// What to do with mentions of an item in its own docs.
+    #[arg(long, value_enum, global = true, default_value_t = SelfLinks::Backtick)]
+    pub self_links: SelfLinks,
+
+    /// Which kinds of mention to link, plain words and/or backticked code spans.
+    #[arg(long, value_enum, global = true, default_value_t = Mentions::Plain)]
+    pub mentions: Mentions,
+
+    /// Rewrite old html path links, i.e `[Foo](struct.Foo.html)`, as intra-doc links, those that
+    /// can't be resolved are reported.
+    #[arg(long, global = true, default_value_t = false)]
+    pub migrate_links: bool,
+
+    /// Only link mentions at least this sure, from 0 to 1, to mean an item rather than plain English.
+    #[arg(long, global = true, default_value_t = 0.0)]
+    pub min_confidence: f32,
+
+    /// Link primitives like `u8` or `&str` to their pages in std rather than just backticking them.
+    #[arg(long, global = true, default_value_t = false)]
+    pub link_primitives: bool,
+
+    /// How items are linked from the README, CHANGELOG and `docs/**/*.md`, markdown included as docs
+    /// is linked like any docs.
+    #[arg(long, value_enum, global = true, default_value_t = MdLinks::DocsRs)]
+    pub md_links: MdLinks,
+
+    /// The settings file, `stklr.toml` in the current directory is used when there is one.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Which mentions of an item to link.
+    #[arg(long, value_enum, global = true, default_value_t = LinkPolicy::All)]
+    pub link_policy: LinkPolicy,
+
+    /// Wrap the mentions the link policy skips in backticks.
+    #[arg(long, global = true, default_value_t = false)]
+    pub backtick_repeats: bool,
+
+    /// Link to private items from public docs too, by default they aren't as rust-doc doesn't
+    /// document private items.
+    #[arg(long, global = true, default_value_t = false)]
+    pub document_private_items: bool,
+
+    /// What to do with mentions of private items in public docs.
+    #[arg(long, value_enum, global = true, default_value_t = PrivateLinks::Skip)]
+    pub private_links: PrivateLinks,
+
+    /// Link std items through their `core`/`alloc` paths, for `#![no_std]` crates, items only in
+    /// `std` aren't linked.
+    #[arg(long, global = true, default_value_t = false)]
+    pub no_std: bool,
+
+    /// How to link items of crates.io dependencies, found from `Cargo.lock` in cargo's local
+    /// source cache.
+    #[arg(long, value_enum, global = true, default_value_t = DepLinks::Path)]
+    pub dep_links: DepLinks,
+
     /// Show a report on % of stuff that's public etc.
     #[command(subcommand)]
     pub command: Commands,
@@ -24,11 +85,59 @@ pub enum Commands {
     /// Will print changes to the terminal, but not write anything.
     Preview { path: Option<Vec<String>> },
     /// Run the app and write changes found.
-    Fix { path: Option<Vec<String>> },
+    Fix {
+        path: Option<Vec<String>>,
+        /// Read one file from stdin and write it, fixed, to stdout, for editors' format-on-save.
+        #[arg(long, requires = "stdin_filename")]
+        stdin: bool,
+        /// Where the file read from stdin lives, its crate on disk is what its idents resolve
+        /// against.
+        #[arg(long, requires = "stdin")]
+        stdin_filename: Option<PathBuf>,
+    },
+    /// Run as an mdBook preprocessor, linking items mentioned in the book to the API docs.
+    Mdbook {
+        #[command(subcommand)]
+        command: Option<MdbookCommand>,
+    },
+    /// Write each item's docs to a markdown file of its own under `dir`.
+    ExportDocs { dir: PathBuf },
+    /// Write the markdown files under `dir`, edited since `export-docs`, back into the docs.
+    ImportDocs { dir: PathBuf },
+    /// Run as a language server on stdio, for editors.
+    Lsp,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MdbookCommand {
+    /// Asked by mdBook, exits successfully if `renderer` is supported.
+    Supports { renderer: String },
 }
 
 impl Cli {
     pub fn init() -> Self {
         Cli::parse()
     }
+
+    /// The [`Config`] the rest of the app runs with.
+    pub fn config(&self) -> Config {
+        Config {
+            self_links: self.self_links,
+            link_policy: self.link_policy,
+            backtick_repeats: self.backtick_repeats,
+            document_private_items: self.document_private_items,
+            private_links: self.private_links,
+            no_std: self.no_std,
+            dep_links: self.dep_links,
+            mentions: self.mentions,
+            migrate_links: self.migrate_links,
+            min_confidence: self.min_confidence,
+            link_primitives: self.link_primitives,
+            md_links: self.md_links,
+            config_file: self.config.clone().or_else(|| {
+                let default = Path::new("stklr.toml");
+                default.exists().then(|| default.to_path_buf())
+            }),
+        }
+    }
 }
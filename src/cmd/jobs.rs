@@ -1,75 +1,436 @@
 use crate::{
-    cmd::cli::{Cli, Commands},
+    cmd::cli::{Cli, Commands, MdbookCommand},
     green, red,
-    search::utils::{ReportCard, SourceTree},
+    search::{
+        config::{Config, ConfigFile, DepLinks, MdLinks},
+        export::{export_docs, import_docs, DocExport},
+        glossary::Glossary,
+        lsp,
+        markdown::{find_markdown, link_markdown},
+        mdbook,
+        migrate::migrate_html_links,
+        rules::{builtin_rules, Rule},
+        shorthand::expand_shorthand,
+        std_index::std_targets,
+        utils::{RawSourceCode, ReportCard, SourceTree},
+        workspace::{find_root, setup_trees, Workspace},
+    },
 };
 
 use ansi_term::Colour;
-use anyhow::Result;
-use std::{collections::HashMap, process::Command};
+use anyhow::{bail, Result};
+use std::{collections::HashMap, fs, io::Read, path::Path};
 
 pub fn run(paths: &Option<Vec<String>>, cli: &Cli) -> Result<()> {
+    run_with(paths, cli, builtin_rules())
+}
+
+/// [`run`] with `rules` in place of the built-in ones, a wrapper binary's way to add its own. The
+/// glossary and `[[rules]]` of the config file run after them.
+pub fn run_with(paths: &Option<Vec<String>>, cli: &Cli, rules: Vec<Box<dyn Rule>>) -> Result<()> {
     let t1 = std::time::Instant::now();
     let mut change_count = 0;
 
-    let st = SourceTree::setup_tree(paths);
+    let cfg = cli.config();
+    let rules = with_config_rules(rules, &cfg)?;
+    let mut trees = setup_trees(paths, &cfg);
+    let std = std_targets(cfg.no_std);
+    trees.iter_mut().for_each(|st| st.add_targets(&std));
 
-    for rsc in st.source_files.iter() {
-        let new_m = rsc
-            .make_adjustments(&rsc.named_idents)
-            .into_iter()
-            .map(|adj| (adj.line_num, adj.contents))
-            .collect::<HashMap<usize, String>>();
+    // Nothing is written unless every file can be fixed.
+    let mut fixed = Vec::new();
+    let mut broken = Vec::new();
+    for (st, rsc) in trees
+        .iter()
+        .flat_map(|st| st.source_files.iter().map(move |rsc| (st, rsc)))
+    {
+        match fixed_lines(rsc, st, &rules, &cfg) {
+            Ok(lines) => fixed.push((rsc, lines)),
+            Err(e) => broken.push(e.to_string()),
+        }
+    }
+    if !broken.is_empty() {
+        bail!("{}", broken.join(", "));
+    }
 
-        let output = (0..rsc.total_lines)
-            .into_iter()
-            .map(|n| -> String {
-                if let Some(new) = new_m.get(&n) {
-                    change_count += 1;
-                    if !cli.quiet {
-                        green!(new, n)
+    for (rsc, lines) in fixed {
+        if !cli.quiet {
+            for (n, (line, notes)) in lines.iter().enumerate() {
+                match notes {
+                    Some(notes) => {
+                        green!(line, n);
+                        for note in notes {
+                            println!("    {}", Colour::Cyan.paint(note));
+                        }
                     }
-                    new.to_owned()
-                } else {
-                    let new = rsc.get(&n).unwrap().contents.to_owned();
-                    if !cli.quiet {
-                        red!(new, n);
-                    }
-                    new
+                    None => red!(line, n),
                 }
-            })
-            .collect::<Vec<String>>();
+            }
+        }
+        change_count += lines.iter().filter(|(_, notes)| notes.is_some()).count();
+
+        if let Commands::Fix { .. } = &cli.command {
+            let output = lines.into_iter().map(|(line, _)| line);
+            std::fs::write(&rsc.file, output.collect::<Vec<String>>().join("\n"))?;
+        }
+    }
 
-        if let Commands::Fix { path } = &cli.command {
-            std::fs::write(&rsc.file, output.join("\n"))?;
+    let mut md_count = 0;
+    if cfg.md_links != MdLinks::Skip {
+        for st in trees.iter() {
+            for md in find_markdown(st) {
+                md_count += 1;
+                let new_m = link_markdown(&md, st, &cfg)
+                    .into_iter()
+                    .collect::<HashMap<usize, String>>();
+                let output = md
+                    .lines
+                    .iter()
+                    .enumerate()
+                    .map(|(n, line)| match new_m.get(&n) {
+                        Some(new) => {
+                            change_count += 1;
+                            if !cli.quiet {
+                                green!(new, n);
+                            }
+                            new.to_owned()
+                        }
+                        None => {
+                            if !cli.quiet {
+                                red!(line, n);
+                            }
+                            line.to_owned()
+                        }
+                    })
+                    .collect::<Vec<String>>();
+
+                if let Commands::Fix { .. } = &cli.command {
+                    if !new_m.is_empty() {
+                        std::fs::write(&md.file, output.join("\n") + "\n")?;
+                    }
+                }
+            }
         }
     }
-    _ = cargo_fmt();
 
     println!(
         "\n\nCOMPLETE!\n{} CHANGES ON {} FILES IN: {}s",
         change_count,
-        st.source_files.len(),
+        trees.iter().map(|st| st.source_files.len()).sum::<usize>() + md_count,
         t1.elapsed().as_secs_f64()
     );
 
     Ok(())
 }
 
-pub fn run_report(paths: &Option<Vec<String>>, _cli: &Cli) -> Result<()> {
+/// A line as fixing its file leaves it, along with what changed it if anything did.
+type FixedLine = (String, Option<Vec<String>>);
+
+/// The lines of `rsc`, one of the files of `st`, once fixed: links written by hand, shorthand and
+/// old html paths, rewritten first so what they become is linked like anything else, then `rules`
+/// applied. Shorthand naming items that don't exist is an error, each is reported as it's found.
+fn fixed_lines(
+    rsc: &RawSourceCode,
+    st: &SourceTree,
+    rules: &[Box<dyn Rule>],
+    cfg: &Config,
+) -> Result<Vec<FixedLine>> {
+    let mut rsc = rsc.clone();
+    let (mut rewritten, missing) = expand_shorthand(&mut rsc, &st.symbols, cfg);
+    for u in missing.iter() {
+        eprintln!(
+            "{} {}:{} {}",
+            Colour::Red.paint("BROKEN"),
+            u.source_file.display(),
+            u.line_num + 1,
+            u.dest
+        );
+    }
+    if !missing.is_empty() {
+        bail!(
+            "{} shorthand link(s) in {} name items that don't exist",
+            missing.len(),
+            rsc.file.display()
+        );
+    }
+    if cfg.migrate_links {
+        let (migrated, unresolved) = migrate_html_links(&mut rsc, &st.symbols);
+        for u in unresolved {
+            eprintln!(
+                "{} {}:{} {}",
+                Colour::Yellow.paint("UNRESOLVED"),
+                u.source_file.display(),
+                u.line_num + 1,
+                u.dest
+            );
+        }
+        rewritten.extend(migrated);
+    }
+
+    let mut notes = rewritten
+        .into_iter()
+        .map(|n| (n, Vec::new()))
+        .collect::<HashMap<usize, Vec<String>>>();
+    let mut new_m = HashMap::new();
+    for adj in rsc.make_adjustments(&rsc.named_idents, &st.symbols, rules, cfg) {
+        let why = adj
+            .edits
+            .iter()
+            .map(|e| format!("{}: {}", e.rule, e.message));
+        notes.insert(adj.line_num, why.collect());
+        new_m.insert(adj.line_num, adj.contents);
+    }
+    Ok((0..rsc.total_lines)
+        .map(|n| {
+            let line = new_m
+                .remove(&n)
+                .or_else(|| rsc.get(&n).map(|rl| rl.contents.to_owned()))
+                .unwrap_or_default();
+            (line, notes.remove(&n))
+        })
+        .collect())
+}
+
+/// The contents of `rsc`, one of the files of `st`, once fixed, see [`fixed_lines`].
+fn fixed_contents(
+    rsc: &RawSourceCode,
+    st: &SourceTree,
+    rules: &[Box<dyn Rule>],
+    cfg: &Config,
+) -> Result<String> {
+    Ok(fixed_lines(rsc, st, rules, cfg)?
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Fixes the one file on stdin, writing it to stdout, as though it were at `file`. Nothing else
+/// goes to stdout, so editors can swap the buffer for it as they would rustfmt's output.
+pub fn run_stdin(file: &Path, cli: &Cli) -> Result<()> {
+    let cfg = cli.config();
+    let rules = with_config_rules(builtin_rules(), &cfg)?;
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+
+    let file = std::env::current_dir()?.join(file);
+    print!("{}", fix_stdin(&file, &input, &rules, &cfg)?);
+    Ok(())
+}
+
+/// What [`run_stdin`] writes for `input`, the contents of `file` which needn't be saved yet. Its
+/// idents resolve against the crate on disk it's in, wherever stklr is run from.
+fn fix_stdin(file: &Path, input: &str, rules: &[Box<dyn Rule>], cfg: &Config) -> Result<String> {
+    let file = match file.parent().and_then(|dir| dir.canonicalize().ok()) {
+        Some(dir) => dir.join(file.file_name().unwrap_or_default()),
+        None => file.to_path_buf(),
+    };
+    let trees = match file.parent().and_then(find_root) {
+        Some(root) => match Workspace::from_dir(&root, cfg) {
+            Ok(ws) => ws.crates.into_iter().map(|c| c.tree).collect(),
+            Err(e) => {
+                log::debug!("Not a cargo project, {:?}", e);
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+    // The crate it's in, or would be in were it saved, is the deepest one containing it.
+    let mut tree = trees
+        .into_iter()
+        .filter(|st| matches!(&st.root, Some(root) if file.starts_with(root)))
+        .max_by_key(|st| st.root.as_ref().map(|r| r.components().count()))
+        .unwrap_or_default();
+    tree.add_targets(&std_targets(cfg.no_std));
+    let st = tree.with_contents(&file, input);
+    let rsc = st
+        .source_files
+        .iter()
+        .find(|rsc| rsc.file == file)
+        .expect("with_contents always adds the file");
+
+    let mut fixed = fixed_contents(rsc, &st, rules, cfg)?;
+    if input.ends_with('\n') {
+        fixed.push('\n');
+    }
+    Ok(fixed)
+}
+
+/// `rules` followed by the glossary and `[[rules]]` of the config file, if there is one.
+fn with_config_rules(mut rules: Vec<Box<dyn Rule>>, cfg: &Config) -> Result<Vec<Box<dyn Rule>>> {
+    if let Some(path) = &cfg.config_file {
+        let file = ConfigFile::read(path)?;
+        if !file.glossary.is_empty() {
+            rules.push(Box::new(Glossary::new(file.glossary.clone())));
+        }
+        for rule in file.replace_rules()? {
+            rules.push(Box::new(rule));
+        }
+    }
+    Ok(rules)
+}
+
+pub fn run_report(paths: &Option<Vec<String>>, cli: &Cli) -> Result<()> {
     let t1 = std::time::Instant::now();
 
-    let st = SourceTree::setup_tree(paths);
-    let rc = ReportCard::from_source_tree(st);
+    // Nothing is linked, so there's no need to index dependencies.
+    let cfg = Config {
+        dep_links: DepLinks::Skip,
+        ..cli.config()
+    };
+    let glossary = match &cfg.config_file {
+        Some(path) => Glossary::new(ConfigFile::read(path)?.glossary),
+        None => Glossary::default(),
+    };
+    for st in setup_trees(paths, &cfg) {
+        let usage = glossary
+            .usage(&st)
+            .into_iter()
+            .map(|(term, locs)| (term.to_string(), locs))
+            .collect::<Vec<_>>();
+        let rc = ReportCard::from_source_tree(st);
+        rc.pretty_print();
 
-    rc.pretty_print();
+        if !glossary.terms.is_empty() {
+            println!("\nGLOSSARY:");
+            for term in glossary.terms.keys() {
+                let locs = usage
+                    .iter()
+                    .find(|(t, _)| t == term)
+                    .map(|(_, locs)| locs.as_slice())
+                    .unwrap_or_default();
+                println!(" {} ({}):", term, locs.len());
+                for (file, n) in locs {
+                    println!("   {}:{}", file.display(), n + 1);
+                }
+            }
+        }
+    }
 
     println!("\n\nCOMPLETED in {}s", t1.elapsed().as_secs_f64());
     Ok(())
 }
 
-pub fn cargo_fmt() -> Result<()> {
-    let cmd = Command::new("cargo fmt").output()?;
-    dbg!("cargo fmt exit code {}", cmd.status);
+/// Speaks mdBook's preprocessor protocol, the book comes in on stdin and goes back out on stdout.
+pub fn run_mdbook(command: &Option<MdbookCommand>, cli: &Cli) -> Result<()> {
+    if let Some(MdbookCommand::Supports { renderer }) = command {
+        if !mdbook::supports(renderer) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    println!("{}", mdbook::preprocess(&input, &cli.config())?);
+    Ok(())
+}
+
+/// Writes the docs of every item to `dir`, one markdown file each.
+pub fn run_export(dir: &Path, cli: &Cli) -> Result<()> {
+    // Nothing is linked, so there's no need to index dependencies.
+    let cfg = Config {
+        dep_links: DepLinks::Skip,
+        ..cli.config()
+    };
+    let cwd = std::env::current_dir()?;
+    let mut count = 0;
+    for st in setup_trees(&None, &cfg) {
+        for export in export_docs(&st, &cwd) {
+            let path = dir.join(export.file_name());
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, export.to_markdown())?;
+            count += 1;
+        }
+    }
+    println!("EXPORTED {} ITEMS TO {}", count, dir.display());
     Ok(())
 }
+
+/// Writes the markdown files under `dir` back into the docs they were exported from, nothing is
+/// written if any of them conflict.
+pub fn run_import(dir: &Path, cli: &Cli) -> Result<()> {
+    let cfg = Config {
+        dep_links: DepLinks::Skip,
+        ..cli.config()
+    };
+    let cwd = std::env::current_dir()?;
+    let mut imports = Vec::new();
+    for entry in glob::glob(&format!("{}/**/*.md", dir.display()))?.flatten() {
+        match DocExport::from_markdown(&fs::read_to_string(&entry)?) {
+            Some(import) => imports.push(import),
+            None => log::warn!("{} has no stklr header, skipping it", entry.display()),
+        }
+    }
+
+    let trees = setup_trees(&None, &cfg);
+    let mut changed = Vec::new();
+    for st in trees.iter() {
+        let ours = imports
+            .iter()
+            .filter(|i| {
+                st.source_files
+                    .iter()
+                    .any(|rsc| rsc.file.ends_with(&i.file))
+            })
+            .cloned()
+            .collect::<Vec<DocExport>>();
+        changed.extend(import_docs(st, &ours, &cwd)?);
+    }
+    for (file, contents) in changed.iter() {
+        fs::write(file, contents)?;
+        println!("{} {}", Colour::Green.paint("IMPORTED"), file.display());
+    }
+    println!("IMPORTED DOCS INTO {} FILES", changed.len());
+    Ok(())
+}
+
+/// Runs the language server on stdio, for as long as the editor that started it wants.
+pub fn run_lsp(cli: &Cli) -> Result<()> {
+    let cfg = cli.config();
+    let rules = with_config_rules(builtin_rules(), &cfg)?;
+    let stdin = std::io::stdin();
+    lsp::serve(&mut stdin.lock(), &mut std::io::stdout(), cfg, rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+
+    #[test]
+    fn fixes_stdin_against_its_crate() {
+        let dir = TestDir::new("stdin");
+        dir.write(
+            "Cargo.toml",
+            "[package]\nname = \"piped\"\nversion = \"0.1.0\"\n",
+        );
+        dir.write("src/lib.rs", "pub mod wire;\npub struct Thing;\n");
+        let cfg = Config {
+            dep_links: DepLinks::Skip,
+            ..Default::default()
+        };
+        let rules = builtin_rules();
+
+        // Unsaved, and in a crate other than the one the tests run from.
+        let wire = dir.join("src/wire.rs");
+        let fixed = fix_stdin(
+            &wire,
+            "/// Sends a Thing.\npub fn send() {}\n",
+            &rules,
+            &cfg,
+        );
+        assert_eq!(
+            fixed.unwrap(),
+            "/// Sends a [`Thing`](crate::Thing).\npub fn send() {}\n"
+        );
+        assert!(fix_stdin(
+            &wire,
+            "/// See [[Nothing]].\npub fn send() {}",
+            &rules,
+            &cfg
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,7 @@
+#![allow(non_snake_case)]
 pub mod cmd;
 mod feedback;
-mod search;
+pub mod search;
 pub mod termite;
 
 use std::{
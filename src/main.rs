@@ -10,7 +10,7 @@
 use STKLR::{
     cmd::{
         cli::{Cli, Commands},
-        jobs::{run, run_report},
+        jobs::{run, run_export, run_import, run_lsp, run_mdbook, run_report, run_stdin},
     },
     termite,
 };
@@ -24,11 +24,17 @@ fn main() -> Result<()> {
         termite::setup_logger().unwrap();
     }
 
-    _ = match &cli.command {
+    match &cli.command {
         Commands::Report { path } => run_report(path, &cli),
         Commands::Preview { path } => run(path, &cli),
-        Commands::Fix { path } => run(path, &cli),
-    };
-
-    Ok(())
+        Commands::Fix {
+            stdin_filename: Some(file),
+            ..
+        } => run_stdin(file, &cli),
+        Commands::Fix { path, .. } => run(path, &cli),
+        Commands::Mdbook { command } => run_mdbook(command, &cli),
+        Commands::ExportDocs { dir } => run_export(dir, &cli),
+        Commands::ImportDocs { dir } => run_import(dir, &cli),
+        Commands::Lsp => run_lsp(&cli),
+    }
 }
@@ -0,0 +1,153 @@
+//!
+//! How sure we are that a word in prose is a mention of an item and not just English, names like
+//! `Report`, `run` or `process` turn up in ordinary sentences all the time.
+//!
+use super::consts::{RUST_CODE_SPAN, RUST_PROSE_PATH};
+
+use std::collections::HashMap;
+
+/// Everyday English words that are also popular item names.
+const DICTIONARY: &[&str] = &[
+    "add", "apply", "block", "build", "call", "check", "clear", "close", "config", "count", "data",
+    "default", "delete", "entry", "error", "event", "file", "filter", "find", "fix", "flag",
+    "format", "get", "handle", "index", "insert", "item", "job", "join", "key", "kind", "level",
+    "line", "link", "linked", "list", "load", "main", "make", "map", "match", "message", "mode",
+    "name", "new", "node", "open", "option", "page", "parse", "path", "pop", "preview", "print",
+    "process", "push", "put", "read", "remove", "render", "report", "reset", "result", "run",
+    "save", "send", "set", "setting", "settings", "size", "sort", "source", "split", "start",
+    "state", "step", "stop", "summary", "table", "target", "task", "test", "text", "tree", "type",
+    "update", "value", "word", "work", "write",
+];
+
+/// Words that come before an item's name when it's being talked about, i.e "the `Foo` struct".
+const DETERMINERS: &[&str] = &[
+    "the", "a", "an", "this", "that", "each", "every", "our", "its", "your", "any",
+];
+
+/// Words that name what an item is, i.e "the `Foo` struct".
+const KINDS: &[&str] = &[
+    "struct", "enum", "trait", "type", "fn", "function", "method", "macro", "module", "mod",
+    "const", "constant", "static", "union", "variant", "field", "crate",
+];
+
+/// How often each word is written as code, in a code span or link, versus as plain prose across
+/// the docs of a tree.
+#[derive(Default, Debug, Clone)]
+pub struct WordUsage {
+    /// `(as code, as prose)`
+    counts: HashMap<String, (usize, usize)>,
+}
+
+impl WordUsage {
+    /// Counts the words of doc `lines`.
+    pub fn from_lines<'a, I>(lines: I) -> Self
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut usage = WordUsage::default();
+        for line in lines {
+            for span in RUST_CODE_SPAN.captures_iter(line) {
+                for word in RUST_PROSE_PATH.find_iter(&span["code"]) {
+                    usage.counts.entry(word.as_str().into()).or_default().0 += 1;
+                }
+            }
+            let prose = RUST_CODE_SPAN.replace_all(line, " ");
+            for word in RUST_PROSE_PATH.find_iter(&prose) {
+                usage.counts.entry(word.as_str().into()).or_default().1 += 1;
+            }
+        }
+        usage
+    }
+
+    /// The share of uses of `word` that are as code, if it's used at all.
+    pub fn code_ratio(&self, word: &str) -> Option<f32> {
+        let (code, prose) = self.counts.get(word)?;
+        match code + prose {
+            0 => None,
+            total => Some(*code as f32 / total as f32),
+        }
+    }
+}
+
+/// How sure we are, from 0 to 1, that `text` (a mention of the item `word`) means the item, given
+/// the words either side of it and how the word is written elsewhere.
+pub fn score(
+    word: &str,
+    text: &str,
+    before: Option<&str>,
+    after: Option<&str>,
+    usage: &WordUsage,
+) -> f32 {
+    let mut score = 0.5;
+    let clean = |w: &str| {
+        w.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    };
+
+    // Nobody writes `snake_case`, `CamelCase`, paths or calls in plain English.
+    let inner_caps =
+        word.chars().skip(1).any(|c| c.is_uppercase()) && word.chars().any(|c| c.is_lowercase());
+    let code_like = inner_caps
+        || text.contains("::")
+        || text.contains("()")
+        || text.contains('!')
+        || word.contains('_')
+        || word.chars().any(|c| c.is_ascii_digit());
+    let sentence_start = before
+        .map(|b| b.ends_with(['.', '!', '?', ':']) || b.starts_with("//"))
+        .unwrap_or(true);
+
+    if code_like {
+        score += 0.4;
+    } else if word.starts_with(char::is_uppercase) && !sentence_start {
+        score += 0.15;
+    } else if !word.starts_with(char::is_uppercase) {
+        score -= 0.1;
+    }
+    if DICTIONARY.contains(&word.to_lowercase().as_str()) {
+        score -= 0.25;
+    }
+
+    let determined = before
+        .map(|b| DETERMINERS.contains(&clean(b).as_str()))
+        .unwrap_or(false);
+    let kind = after
+        .map(|a| KINDS.contains(&clean(a).as_str()))
+        .unwrap_or(false);
+    match (determined, kind) {
+        (true, true) => score += 0.3,
+        (false, true) => score += 0.2,
+        _ => (),
+    }
+
+    // Mostly written as code elsewhere makes this likely code too, and vice versa.
+    if let Some(ratio) = usage.code_ratio(word) {
+        score += 0.3 * (ratio - 0.5);
+    }
+    score.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_scores_lower() {
+        let usage = WordUsage::from_lines(
+            ["/// Run the report, then `SourceTree` the report again."].into_iter(),
+        );
+        let plain = score("report", "report", Some("the"), Some("again"), &usage);
+        let code = score(
+            "SourceTree",
+            "SourceTree",
+            Some("the"),
+            Some("struct"),
+            &usage,
+        );
+        let call = score("run", "run()", Some("then"), None, &usage);
+
+        assert!(plain < 0.5, "{}", plain);
+        assert!(code > 0.9, "{}", code);
+        assert!(call > plain, "{} {}", call, plain);
+    }
+}
@@ -0,0 +1,141 @@
+//!
+//! Settings that control how docs are modified.
+//!
+use super::replace::{RegexRule, ReplaceRule};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What to do with mentions of an item inside its own documentation, linking them just creates
+/// noise in the rendered docs.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfLinks {
+    /// Leave them exactly as they are.
+    Skip,
+    /// Wrap them in `backticks` instead of linking.
+    #[default]
+    Backtick,
+}
+
+/// What to do with mentions of private items in public docs, linking them is what rust-doc's
+/// `private_intra_doc_links` lint warns about.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivateLinks {
+    /// Leave them exactly as they are.
+    #[default]
+    Skip,
+    /// Wrap them in `backticks` instead of linking.
+    Backtick,
+}
+
+/// Which mentions of an item get linked, the Rust API guidelines recommend linking only the first
+/// mention of an item rather than every single one.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkPolicy {
+    /// Link every mention.
+    #[default]
+    All,
+    /// Link the first mention in each doc block.
+    FirstPerBlock,
+    /// Link the first mention in each paragraph of a doc block.
+    FirstPerParagraph,
+}
+
+/// Which mentions of an item are candidates for linking at all.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mentions {
+    /// Plain words, i.e SourceTree.
+    #[default]
+    Plain,
+    /// Code spans that name an item exactly, i.e `SourceTree`, `SourceTree::new()` or `run()`.
+    Backticked,
+    /// Both of the above.
+    Both,
+}
+
+impl Mentions {
+    pub fn plain(&self) -> bool {
+        matches!(self, Mentions::Plain | Mentions::Both)
+    }
+
+    pub fn backticked(&self) -> bool {
+        matches!(self, Mentions::Backticked | Mentions::Both)
+    }
+}
+
+/// How items of crates.io dependencies are linked.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepLinks {
+    /// Don't link them at all.
+    Skip,
+    /// By their crate-qualified path, i.e [`regex::Regex`].
+    #[default]
+    Path,
+    /// To their docs.rs page, pinned to the version in `Cargo.lock`.
+    DocsRs,
+}
+
+/// How crate items are linked from markdown files, where intra-doc links don't work.
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MdLinks {
+    /// Leave markdown files alone.
+    Skip,
+    /// To their page on docs.rs.
+    #[default]
+    DocsRs,
+    /// To their page in `target/doc`, as `cargo doc` builds it.
+    Relative,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Config {
+    pub self_links: SelfLinks,
+    pub link_policy: LinkPolicy,
+    /// Wrap the mentions [`LinkPolicy`] doesn't link in backticks.
+    pub backtick_repeats: bool,
+    /// Link to private items too, mirroring rust-doc's flag of the same name.
+    pub document_private_items: bool,
+    pub private_links: PrivateLinks,
+    /// Link std items through `core`/`alloc` instead, for `#![no_std]` crates.
+    pub no_std: bool,
+    pub dep_links: DepLinks,
+    pub mentions: Mentions,
+    /// Rewrite rust-doc's old html path links, i.e `[Foo](struct.Foo.html)`, as intra-doc links.
+    pub migrate_links: bool,
+    /// Mentions scoring lower than this, see [`super::confidence::score`], aren't linked.
+    pub min_confidence: f32,
+    /// Link the primitives [`super::ticks`] backticks to their pages in `std`.
+    pub link_primitives: bool,
+    pub md_links: MdLinks,
+    /// Where the [`ConfigFile`] is, if there is one.
+    pub config_file: Option<PathBuf>,
+}
+
+/// The settings file, `stklr.toml`, for what's too much to pass as flags.
+#[derive(Deserialize, Default, Debug, Clone)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub rules: Vec<ReplaceRule>,
+    /// Terms linked like crate items, to a URL or an intra-doc path, see [`super::glossary`].
+    #[serde(default)]
+    pub glossary: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+
+    /// The `[[rules]]`, compiled.
+    pub fn replace_rules(&self) -> Result<Vec<RegexRule>> {
+        self.rules.iter().map(RegexRule::new).collect()
+    }
+}
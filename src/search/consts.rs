@@ -1,21 +1,99 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 
+/// The optional visibility every item declaration may start with, i.e `pub`, `pub(crate)`,
+/// `pub(in crate::foo)` etc.
+const VIS: &str = r"^\s*(?:pub(?:\s*\([^)]*\))?\s+)?";
+
+/// A generic parameter not worth linking, a single letter, a lifetime or a primitive.
+const PARAM: &str = r"&?(?:'\w+|\p{Lu}|bool|char|str|[iu](?:8|16|32|64|128|size)|f32|f64)";
+
 lazy_static! {
-    //TODO: can you be DRYer?
-    pub static ref RUST_DOCSTRING: Regex = Regex::new(r#"(?P<ident>(///))"#).unwrap();
-    pub static ref RUST_ENUM: Regex = Regex::new(r"(enum\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_FN: Regex = Regex::new(r"(fn\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_STRUCT: Regex = Regex::new(r"(struct\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_TRAIT: Regex = Regex::new(r"(trait\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_TY: Regex = Regex::new(r"(type\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_USE: Regex = Regex::new(r"(use\s{1}(?P<ident>\w*))").unwrap();
-    pub static ref RUST_IMPORT: Regex = Regex::new(r"(::{1}(?P<ident>\w*;))").unwrap();
+    pub static ref RUST_VIS: Regex =
+        Regex::new(r"^\s*pub(?:\s*\(\s*(?P<scope>[^)]*?)\s*\))?\s").unwrap();
+    pub static ref RUST_DOCSTRING: Regex = Regex::new(r#"^\s*(?P<ident>(///|//!))"#).unwrap();
+    pub static ref RUST_ENUM: Regex = Regex::new(&format!(r"{VIS}enum\s+(?P<ident>\w+)")).unwrap();
+    pub static ref RUST_FN: Regex = Regex::new(&format!(
+        r#"{VIS}(?:(?:const|async|unsafe|default)\s+)*(?:extern\s+(?:"[^"]*"\s+)?)?fn\s+(?P<ident>\w+)"#
+    ))
+    .unwrap();
+    pub static ref RUST_STRUCT: Regex = Regex::new(&format!(r"{VIS}struct\s+(?P<ident>\w+)")).unwrap();
+    pub static ref RUST_UNION: Regex = Regex::new(&format!(r"{VIS}union\s+(?P<ident>\w+)")).unwrap();
+    pub static ref RUST_TRAIT: Regex =
+        Regex::new(&format!(r"{VIS}(?:unsafe\s+)?(?:auto\s+)?trait\s+(?P<ident>\w+)")).unwrap();
+    pub static ref RUST_TY: Regex = Regex::new(&format!(r"{VIS}type\s+(?P<ident>\w+)")).unwrap();
+    // NOTE: the trailing ':' is what separates a `const FOO: T` from a `const fn`.
+    pub static ref RUST_CONST: Regex = Regex::new(&format!(r"{VIS}const\s+(?P<ident>\w+)\s*:")).unwrap();
+    pub static ref RUST_STATIC: Regex =
+        Regex::new(&format!(r"{VIS}static\s+(?:mut\s+)?(?P<ident>\w+)\s*:")).unwrap();
+    pub static ref RUST_MOD: Regex = Regex::new(&format!(r"{VIS}mod\s+(?P<ident>\w+)")).unwrap();
+    pub static ref RUST_MACRO: Regex =
+        Regex::new(r"^\s*(?:#\[macro_export\]\s*)?macro_rules!\s*(?P<ident>\w+)").unwrap();
+    // The ident of an impl is the type being implemented on, for both `impl Foo` and
+    // `impl Trait for Foo`.
+    pub static ref RUST_IMPL: Regex = Regex::new(
+        r"^\s*(?:unsafe\s+)?impl(?:\s*<[^{]*?>)?\s+(?:!?[\w:]+(?:<[^{]*?>)?\s+for\s+)?&?(?:\w+::)*(?P<ident>\w+)"
+    )
+    .unwrap();
+    // The name an item of an `impl` or `trait` is declared with, linkable or not, i.e `new`.
+    pub static ref RUST_MEMBER: Regex = Regex::new(&format!(
+        r#"{VIS}(?:(?:const|async|unsafe|default)\s+)*(?:extern\s+(?:"[^"]*"\s+)?)?(?:fn|type|const)\s+(?P<ident>\w+)"#
+    ))
+    .unwrap();
+    // A variant in the body of an enum, i.e `Fast,`, `Fast(u8)` or `Fast { .. }`.
+    pub static ref RUST_ENUM_VARIANT: Regex =
+        Regex::new(r"^\s*(?P<ident>\p{Lu}\w*)\s*(?:[,({=]|$)").unwrap();
+    // The start of a `use` statement, see ./src/search/uses.rs for how the tree itself is parsed.
+    pub static ref RUST_USE: Regex = Regex::new(&format!(r"{VIS}use\s+(?P<tree>.*)")).unwrap();
+
+    // A path as it may be written in prose, i.e `Foo`, `Foo::bar` or `crate::Foo`, by Rust's own
+    // (Unicode) rules for identifiers so `Tree` is never found in `SourceTree` or `éTree`.
+    pub static ref RUST_PROSE_PATH: Regex =
+        Regex::new(r"[\p{XID_Start}_]\p{XID_Continue}*(?:::[\p{XID_Start}_]\p{XID_Continue}*)*").unwrap();
+
+    // An inline code span, and whether it's already the text of a link, `[` before and `]` after.
+    pub static ref RUST_CODE_SPAN: Regex =
+        Regex::new(r"(?P<open>\[?)`(?P<code>[^`\s]+)`(?P<close>\]?)").unwrap();
+
+    // rust-doc's old html path links, inline, i.e `[Foo](struct.Foo.html)`, and as reference
+    // definitions, i.e `[Foo]: struct.Foo.html`. URLs have a ':' so they're never matched.
+    pub static ref RUST_HTML_LINK: Regex =
+        Regex::new(r"\[(?P<text>[^\]]*)\]\((?P<dest>[\w./-]+\.html(?:#[\w.-]+)?)\)").unwrap();
+    pub static ref RUST_HTML_LINK_DEF: Regex =
+        Regex::new(r"^(?P<def>\s*//[/!]\s*\[[^\]]+\]:\s*)(?P<dest>[\w./-]+\.html(?:#[\w.-]+)?)\s*$").unwrap();
+
+    // Wiki-style shorthand for a link, i.e `[[Foo]]`, `[[Foo::bar]]` or `[[Foo|the foo type]]`.
+    pub static ref RUST_WIKI_LINK: Regex =
+        Regex::new(r"\[\[(?P<path>[^\]|\s]+)(?:\|(?P<text>[^\]]+))?\]\]").unwrap();
+
+    // Attributes that make an item test only, or hide it from the docs.
+    pub static ref RUST_TEST_ATTR: Regex =
+        Regex::new(r"#\[(?:\w+::)*test\]|#\[cfg\((?:all\((?:[^()]*,\s*)?)?test\b").unwrap();
+    pub static ref RUST_DOC_HIDDEN: Regex = Regex::new(r"#\[doc\(hidden\)\]").unwrap();
+    // A markdown file included as docs, i.e `#![doc = include_str!("../README.md")]`.
+    pub static ref RUST_DOC_INCLUDE: Regex =
+        Regex::new(r#"#!?\[doc\s*=\s*include_str!\(\s*"(?P<path>[^"]+\.md)"\s*\)\s*\]"#).unwrap();
+
+    // Code-ish tokens written in prose, see [`super::ticks`]. Primitives, i.e `u8`, `&str` or `()`.
+    pub static ref RUST_PRIMITIVE: Regex =
+        Regex::new(r"^&?(?:bool|char|str|[iu](?:8|16|32|64|128|size)|f32|f64)$|^\(\)$").unwrap();
+    // Suffixed number literals, i.e `0u8` or `1.5f32`, and the bools.
+    pub static ref RUST_LITERAL: Regex =
+        Regex::new(r"^-?\d[\d_]*(?:\.\d[\d_]*)?(?:[iu](?:8|16|32|64|128|size)|f32|f64)$").unwrap();
+    pub static ref RUST_BOOL_LITERAL: Regex = Regex::new(r"^(?:true|false)$").unwrap();
+    // Option and Result variants with something in them, i.e `Some(..)` or `Err(e)`.
+    pub static ref RUST_VARIANT: Regex = Regex::new(r"^(?:Some|Ok|Err)\([^()\s]*\)$").unwrap();
+    pub static ref RUST_KEYWORD: Regex =
+        Regex::new(r"^(?:Self|&?self|&mut|dyn|fn|async|\.await)$").unwrap();
+    // Types generic over parameters, lifetimes or primitives, i.e `Vec<T>`, `Option<&str>` or
+    // `HashMap<K,V>`. Not `Vec<Thing>`, where `Thing` can still be linked.
+    pub static ref RUST_GENERIC: Regex = Regex::new(&format!(
+        r"^&?\p{{Lu}}\w*<{PARAM}(?:,{PARAM})*>$"
+    ))
+    .unwrap();
 
     // Stuff we never want linked.
     pub static ref NEVERS: Vec<&'static str> = vec!["log", "std", "core", "super","io","crate",
         "to", "path", "for","from", "into", "that", "we", "so","of", "new", "file", "from", "into", "self", "Self"];
 
-    // Stuff we only want ` tik wrapped.
-    pub static ref ALWAYS: Vec<&'static str> = vec!["std", "core", "io", "self", "Result", "()", "Self", "String", "str", "&str", "bool", "true", "false", "float", "f8", "f16", "f32", "f64", "f128", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "i128", "u128", "isize", "usize", "path","from","into", "n"];
 }
@@ -0,0 +1,275 @@
+//!
+//! Dependencies from crates.io: the versions `Cargo.lock` pins, where cargo keeps their sources
+//! and which of their items can be linked. Nothing is ever downloaded, crates cargo hasn't fetched
+//! yet are simply skipped.
+//!
+use super::{
+    config::DepLinks,
+    symbols::{LinkTarget, Symbol},
+    utils::{find_source_files, Flavour, SourceTree},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize, Default, Debug)]
+pub struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    /// i.e `"regex"`, or `"regex 1.6.0"` when more than one version is locked.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    fn is_registry(&self) -> bool {
+        self.source
+            .as_deref()
+            .map(|s| s.starts_with("registry+") || s.starts_with("sparse+"))
+            .unwrap_or(false)
+    }
+}
+
+impl Lockfile {
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join("Cargo.lock");
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+
+    /// The locked versions of the registry dependencies of the workspace crate `package`, keyed by
+    /// package name.
+    pub fn registry_deps(&self, package: &str) -> HashMap<String, String> {
+        let krate = match self
+            .package
+            .iter()
+            .find(|p| p.name == package && p.source.is_none())
+        {
+            Some(krate) => krate,
+            None => return HashMap::new(),
+        };
+
+        krate
+            .dependencies
+            .iter()
+            .filter_map(|dep| {
+                let mut parts = dep.split_whitespace();
+                let name = parts.next()?;
+                let version = parts.next();
+                self.package
+                    .iter()
+                    .filter(|p| p.name == name && p.is_registry())
+                    .find(|p| version.map(|v| v == p.version).unwrap_or(true))
+                    .map(|p| (p.name.clone(), p.version.clone()))
+            })
+            .collect()
+    }
+}
+
+/// The flavours of dependency items that get linked.
+pub(crate) const LINKED_FLAVOURS: &[Flavour] = &[
+    Flavour::RUST_STRUCT,
+    Flavour::RUST_ENUM,
+    Flavour::RUST_TRAIT,
+    Flavour::RUST_TY,
+    Flavour::RUST_UNION,
+    Flavour::RUST_MACRO,
+];
+
+/// Cargo's local cache of crate sources, `$CARGO_HOME/registry/src`.
+#[derive(Debug, Default)]
+pub struct Registry {
+    pub cargo_home: PathBuf,
+    /// Each `(name, version)` is only indexed once, no matter how many crates depend on it.
+    trees: HashMap<(String, String), Option<SourceTree>>,
+}
+
+impl Registry {
+    pub fn new(cargo_home: PathBuf) -> Self {
+        Registry {
+            cargo_home,
+            ..Default::default()
+        }
+    }
+
+    /// The registry of `$CARGO_HOME`, or `~/.cargo` when that isn't set.
+    pub fn from_env() -> Self {
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cargo")))
+            .unwrap_or_default();
+        Self::new(cargo_home)
+    }
+
+    /// Where the sources of `name` at `version` were unpacked, if they ever were.
+    fn source_dir(&self, name: &str, version: &str) -> Option<PathBuf> {
+        let pattern = self
+            .cargo_home
+            .join("registry")
+            .join("src")
+            .join("*")
+            .join(format!("{}-{}", name, version));
+        glob::glob(&pattern.display().to_string())
+            .ok()?
+            .filter_map(Result::ok)
+            .find(|dir| dir.is_dir())
+    }
+
+    fn tree(&mut self, name: &str, version: &str) -> Option<&SourceTree> {
+        let key = (name.to_string(), version.to_string());
+        if !self.trees.contains_key(&key) {
+            let tree = self.source_dir(name, version).map(|dir| {
+                debug!("Indexing {} {} from {}", name, version, dir.display());
+                SourceTree::new_from_files(&find_source_files(dir.join("src").display()))
+            });
+            if tree.is_none() {
+                debug!("No local sources for {} {}, skipping", name, version);
+            }
+            self.trees.insert(key.clone(), tree);
+        }
+        self.trees.get(&key).and_then(|t| t.as_ref())
+    }
+
+    /// Public items of the registry crates `package` depends on, `deps` maps their package names
+    /// to the names they're known by in code. Only types, traits and macros are linked, a
+    /// dependency's fns and modules tend to be named like everyday words (`find`, `round`). These
+    /// are added before the std index, so a crate's `anyhow::Result` wins over `std`'s, and a
+    /// file's own imports win over both. Each is linkable by its crate-qualified path as well.
+    pub fn targets(
+        &mut self,
+        lock: &Lockfile,
+        package: &str,
+        deps: &HashMap<String, String>,
+        dep_links: DepLinks,
+    ) -> HashMap<String, LinkTarget> {
+        let mut targets = HashMap::new();
+        if dep_links == DepLinks::Skip {
+            return targets;
+        }
+
+        for (name, version) in lock.registry_deps(package) {
+            let known_as = match deps.get(&name) {
+                Some(known_as) => known_as,
+                // Only direct dependencies are nameable, not what they pull in.
+                None => continue,
+            };
+            let tree = match self.tree(&name, &version) {
+                Some(tree) => tree,
+                None => continue,
+            };
+            let target = |sym: &Symbol| {
+                if !LINKED_FLAVOURS.contains(&sym.flavour) {
+                    return None;
+                }
+                match dep_links {
+                    DepLinks::DocsRs => docs_rs_url(&name, &version, sym).map(LinkTarget::Explicit),
+                    _ => sym.path_from(known_as).map(LinkTarget::Qualified),
+                }
+            };
+            targets.extend(tree.targets_by(target));
+            // And by their crate-qualified path, which is never ambiguous, i.e `anyhow::Result`.
+            targets.extend(
+                tree.symbols
+                    .iter()
+                    .filter(|sym| !sym.associated)
+                    .filter_map(|sym| Some((sym.path_from(known_as)?, target(sym)?))),
+            );
+        }
+        targets
+    }
+}
+
+/// The docs.rs page of `sym`, pinned to `version`, i.e
+/// `https://docs.rs/regex/1.6.0/regex/struct.Regex.html`.
+pub fn docs_rs_url(package: &str, version: &str, sym: &Symbol) -> Option<String> {
+    let (name, module) = sym.canonical()?.split_last()?;
+    let mut url = format!(
+        "https://docs.rs/{}/{}/{}/",
+        package,
+        version,
+        package.replace('-', "_")
+    );
+    module.iter().for_each(|m| url.push_str(&format!("{}/", m)));
+
+    match sym.flavour {
+        Flavour::RUST_MOD => Some(format!("{}{}/index.html", url, name)),
+        _ => Some(format!("{}{}.{}.html", url, sym.flavour.html_kind()?, name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+
+    #[test]
+    fn links_locked_registry_deps() {
+        let dir = TestDir::new("deps");
+        dir.write(
+            "Cargo.lock",
+            r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["fancy-regex", "unused 1.0.0"]
+
+[[package]]
+name = "fancy-regex"
+version = "0.2.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "unused"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        );
+        dir.write(
+            "home/registry/src/index.crates.io-0/fancy-regex-0.2.1/src/lib.rs",
+            "mod re;\npub use re::Regex;\npub type Result<T> = std::result::Result<T, ()>;\n\
+             pub fn escape() {}\n",
+        );
+        dir.write(
+            "home/registry/src/index.crates.io-0/fancy-regex-0.2.1/src/re.rs",
+            "pub struct Regex;\n",
+        );
+
+        let lock = Lockfile::read(dir.path()).unwrap();
+        let deps = HashMap::from([("fancy-regex".to_string(), "fancy_regex".to_string())]);
+        let mut registry = Registry::new(dir.join("home"));
+
+        let paths = registry.targets(&lock, "app", &deps, DepLinks::Path);
+        assert_eq!(
+            paths.get("Regex"),
+            Some(&LinkTarget::Qualified("fancy_regex::Regex".into()))
+        );
+        // Named like something of std's, but it's the dependency's that's meant.
+        assert_eq!(
+            paths.get("Result"),
+            Some(&LinkTarget::Qualified("fancy_regex::Result".into()))
+        );
+        assert_eq!(paths.get("escape"), None);
+
+        let urls = registry.targets(&lock, "app", &deps, DepLinks::DocsRs);
+        assert_eq!(
+            urls.get("Regex"),
+            Some(&LinkTarget::Explicit(
+                "https://docs.rs/fancy-regex/0.2.1/fancy_regex/struct.Regex.html".into()
+            ))
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//!
+//! Grouping of doc comment lines into blocks, and working out which item each block documents.
+//!
+use super::utils::{Flavour, RawLine};
+
+use std::collections::HashMap;
+
+/// A run of consecutive doc comment lines and the item they document.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct DocBlock {
+    pub lines: Vec<usize>,
+    /// The line the documented item is declared on, attributes and blank lines skipped.
+    pub item_line: Option<usize>,
+    /// The (linkable) ident of the documented item.
+    pub owner: Option<String>,
+    /// `//!` docs, which document the enclosing module rather than the next item.
+    pub inner: bool,
+    /// Whether these docs end up in the public documentation.
+    pub public: bool,
+    /// The lines of ```` ``` ```` fenced code, doctests say, their fences included.
+    pub fenced: Vec<usize>,
+}
+
+impl DocBlock {
+    /// Is `ident` the item this block is documenting?
+    pub fn is_owner(&self, ident: &str) -> bool {
+        self.owner.as_deref() == Some(ident)
+    }
+
+    /// Is line `n` code rather than prose?
+    pub fn is_fenced(&self, n: usize) -> bool {
+        self.fenced.contains(&n)
+    }
+}
+
+/// Groups the doc lines in `m` into [`DocBlock`]s, `module` is the name of the module the lines
+/// are from, i.e the owner of any `//!` docs.
+pub fn find_doc_blocks(
+    m: &HashMap<usize, RawLine>,
+    total_lines: usize,
+    module: Option<&str>,
+) -> Vec<DocBlock> {
+    let mut blocks = Vec::new();
+    let mut n = 0;
+
+    while n < total_lines {
+        let inner = match m.get(&n) {
+            Some(rl) if rl.flavour == Flavour::RUST_DOCS => is_inner(rl),
+            _ => {
+                n += 1;
+                continue;
+            }
+        };
+
+        let mut block = DocBlock {
+            inner,
+            ..Default::default()
+        };
+        let mut fenced = false;
+        while let Some(rl) = m.get(&n) {
+            if rl.flavour != Flavour::RUST_DOCS || is_inner(rl) != inner {
+                break;
+            }
+            let text = rl.contents.trim_start()[3..].trim_start();
+            let fence = text.starts_with("```") || text.starts_with("~~~");
+            if fenced || fence {
+                block.fenced.push(n);
+            }
+            fenced ^= fence;
+            block.lines.push(n);
+            n += 1;
+        }
+
+        if inner {
+            block.owner = module.map(|s| s.to_string());
+        } else {
+            block.item_line = find_item_line(m, n, total_lines);
+            block.owner = block
+                .item_line
+                .and_then(|l| m.get(&l))
+                .and_then(|rl| rl.idents.first().cloned());
+        }
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+fn is_inner(rl: &RawLine) -> bool {
+    rl.contents.trim_start().starts_with("//!")
+}
+
+/// Skips attributes (multi-line ones included), blank lines and plain comments from `start` to
+/// find the line the item itself is declared on.
+fn find_item_line(m: &HashMap<usize, RawLine>, start: usize, total_lines: usize) -> Option<usize> {
+    let mut in_attr = false;
+
+    for n in start..total_lines {
+        let text = m.get(&n)?.contents.trim();
+
+        if in_attr {
+            in_attr = !text.ends_with(']');
+            continue;
+        }
+        if text.starts_with("#[") {
+            in_attr = !text.ends_with(']');
+            continue;
+        }
+        if text.is_empty() || (text.starts_with("//") && !text.starts_with("///")) {
+            continue;
+        }
+        return Some(n);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_attributes_and_blanks() {
+        let src = [
+            "//! Module docs.",
+            "/// The docs.",
+            "#[derive(",
+            "    Debug,",
+            ")]",
+            "",
+            "pub struct Documented;",
+        ];
+        let m = src
+            .iter()
+            .enumerate()
+            .map(|(n, l)| {
+                let mut rl = RawLine {
+                    line_num: n,
+                    contents: l.to_string(),
+                    ..Default::default()
+                };
+                rl.find_docs();
+                rl.find_idents();
+                (n, rl)
+            })
+            .collect::<HashMap<usize, RawLine>>();
+
+        let blocks = find_doc_blocks(&m, src.len(), Some("module"));
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].inner && blocks[0].is_owner("module"));
+        assert_eq!(blocks[1].item_line, Some(6));
+        assert!(blocks[1].is_owner("Documented"));
+    }
+
+    #[test]
+    fn tracks_fences() {
+        let src = [
+            "/// Prose.",
+            "///",
+            "/// ```rust",
+            "/// let w: u8 = 1;",
+            "/// ```",
+            "/// More prose.",
+            "pub struct Documented;",
+        ];
+        let m = src
+            .iter()
+            .enumerate()
+            .map(|(n, l)| {
+                let mut rl = RawLine {
+                    line_num: n,
+                    contents: l.to_string(),
+                    ..Default::default()
+                };
+                rl.find_docs();
+                (n, rl)
+            })
+            .collect::<HashMap<usize, RawLine>>();
+
+        let blocks = find_doc_blocks(&m, src.len(), None);
+        assert_eq!(blocks[0].fenced, vec![2, 3, 4]);
+        assert!(!blocks[0].is_fenced(5));
+    }
+}
@@ -0,0 +1,344 @@
+//!
+//! Exporting doc comments to markdown files, one per item, to be edited in a dedicated markdown
+//! tool, and importing them back into the right `///` blocks.
+//!
+use super::{
+    docs::DocBlock,
+    symbols::display_path,
+    utils::{RawSourceCode, SourceTree},
+};
+
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+lazy_static! {
+    // The header identifying the item an exported file documents.
+    static ref HEADER: Regex = Regex::new(
+        r#"^<!-- stklr item="(?P<item>[^"]+)" file="(?P<file>[^"]+)" hash="(?P<hash>[0-9a-f]{16})" -->$"#
+    )
+    .unwrap();
+    // The name an item is declared with, for those that aren't linkable, i.e `new`.
+    static ref DECLARED: Regex = Regex::new(
+        r"\b(?:fn|struct|enum|union|trait|type|const|static|mod|macro_rules!)\s+(?P<ident>\w+)"
+    )
+    .unwrap();
+}
+
+/// The docs of one item, as they're exported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocExport {
+    /// The item's path, i.e `crate::search::utils::SourceTree`, with a `#2` and so on when more
+    /// than one item of a file shares it, like methods of different types.
+    pub item: String,
+    pub file: PathBuf,
+    /// Of the text as it was exported, to tell whether the source has changed since.
+    pub hash: String,
+    /// The docs without their `///`s.
+    pub text: String,
+}
+
+impl DocExport {
+    /// Where the export goes in the export directory, i.e `search/utils/SourceTree.md`, module
+    /// docs being the `index.md` of the module's directory.
+    pub fn file_name(&self) -> PathBuf {
+        let item = self.item.replace('#', "-").replace('!', "");
+        let mut parts = item.split("::").skip(1).collect::<Vec<&str>>();
+        if self.is_module_docs() || parts.is_empty() {
+            parts.push("index");
+        }
+        let mut path = parts.iter().collect::<PathBuf>();
+        path.set_extension("md");
+        path
+    }
+
+    fn is_module_docs(&self) -> bool {
+        self.item.ends_with("::")
+    }
+
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "<!-- stklr item=\"{}\" file=\"{}\" hash=\"{}\" -->\n\n{}\n",
+            self.item,
+            self.file.display(),
+            self.hash,
+            self.text
+        )
+    }
+
+    /// Parses an exported file, `None` if it has no header.
+    pub fn from_markdown(markdown: &str) -> Option<Self> {
+        let (header, text) = markdown.split_once('\n').unwrap_or((markdown, ""));
+        let caps = HEADER.captures(header.trim_end())?;
+        Some(Self {
+            item: caps["item"].to_string(),
+            file: PathBuf::from(&caps["file"]),
+            hash: caps["hash"].to_string(),
+            // Only the newlines around the text that `to_markdown` added, trailing `///`s are kept.
+            text: text
+                .strip_prefix('\n')
+                .and_then(|text| text.strip_suffix('\n'))
+                .unwrap_or(text)
+                .to_string(),
+        })
+    }
+}
+
+/// A 64 bit FNV-1a hash of `text` in hex, the same from one build to the next unlike std's.
+pub fn fnv_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325_u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// The text of a doc block, `///`, `//!` and one space after them stripped.
+fn block_text(rsc: &RawSourceCode, block: &DocBlock) -> String {
+    block
+        .lines
+        .iter()
+        .filter_map(|n| rsc.get(n))
+        .map(|rl| {
+            let line = rl.contents.trim_start();
+            let line = line
+                .strip_prefix("///")
+                .or_else(|| line.strip_prefix("//!"))
+                .unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line).to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Every doc block of `st` that documents an item, along with the item's (unique) name. Paths
+/// are shown relative to `cwd` where they can be.
+fn documented<'a>(
+    st: &'a SourceTree,
+    cwd: &Path,
+) -> Vec<(String, PathBuf, &'a RawSourceCode, &'a DocBlock)> {
+    let mut found = Vec::new();
+    for rsc in st.source_files.iter() {
+        let file = rsc
+            .file
+            .strip_prefix(cwd)
+            .unwrap_or(&rsc.file)
+            .to_path_buf();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for block in rsc.doc_blocks.iter() {
+            let declared = block
+                .item_line
+                .and_then(|l| rsc.get(&l))
+                .and_then(|rl| DECLARED.captures(&rl.contents))
+                .map(|caps| caps["ident"].to_string());
+            let item = match (block.inner, block.owner.clone().or(declared)) {
+                (true, _) => format!("{}::", display_path("crate", &rsc.module_path)),
+                (false, Some(owner)) => st
+                    .symbols
+                    .iter()
+                    .find(|sym| {
+                        sym.source_file == rsc.file && Some(sym.line_num) == block.item_line
+                    })
+                    .map(|sym| display_path("crate", &sym.path))
+                    .unwrap_or_else(|| {
+                        let mut path = rsc.module_path.clone();
+                        path.push(owner);
+                        display_path("crate", &path)
+                    }),
+                (false, None) => continue,
+            };
+            let count = seen.entry(item.clone()).or_default();
+            *count += 1;
+            let item = match count {
+                1 => item,
+                n => format!("{}#{}", item, n),
+            };
+            found.push((item, file.clone(), rsc, block));
+        }
+    }
+    found
+}
+
+/// The docs of every documented item of `st`.
+pub fn export_docs(st: &SourceTree, cwd: &Path) -> Vec<DocExport> {
+    documented(st, cwd)
+        .into_iter()
+        .map(|(item, file, rsc, block)| {
+            let text = block_text(rsc, block);
+            DocExport {
+                item,
+                file,
+                hash: fnv_hash(&text),
+                text,
+            }
+        })
+        .collect()
+}
+
+/// The new contents of each source file of `st` the `imports` change, re-indented and re-prefixed
+/// to match the blocks they replace. Unedited exports are skipped, whatever became of their block
+/// since. Nothing is returned, but an error listing them, if any block was changed in the source
+/// since it was exported as well as in its export.
+pub fn import_docs(
+    st: &SourceTree,
+    imports: &[DocExport],
+    cwd: &Path,
+) -> Result<Vec<(PathBuf, String)>> {
+    let documented = documented(st, cwd);
+    let mut conflicts = Vec::new();
+    let mut replacements: HashMap<PathBuf, Vec<(&DocBlock, Vec<String>)>> = HashMap::new();
+
+    for import in imports {
+        let Some((_, _, rsc, block)) = documented
+            .iter()
+            .find(|(item, file, _, _)| *item == import.item && *file == import.file)
+        else {
+            conflicts.push(format!("{} no longer exists", import.item));
+            continue;
+        };
+        let current = block_text(rsc, block);
+        if current == import.text || fnv_hash(&import.text) == import.hash {
+            continue;
+        }
+        if fnv_hash(&current) != import.hash {
+            conflicts.push(format!(
+                "{} was changed in {} since it was exported",
+                import.item,
+                import.file.display()
+            ));
+            continue;
+        }
+
+        let first = rsc
+            .get(&block.lines[0])
+            .map(|rl| rl.contents.as_str())
+            .unwrap_or_default();
+        let indent = &first[..first.len() - first.trim_start().len()];
+        let prefix = match block.inner {
+            true => "//!",
+            false => "///",
+        };
+        let lines = import
+            .text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .map(|line| match line.is_empty() {
+                true => format!("{}{}", indent, prefix),
+                false => format!("{}{} {}", indent, prefix, line),
+            })
+            .collect();
+        replacements
+            .entry(rsc.file.clone())
+            .or_default()
+            .push((block, lines));
+    }
+    if !conflicts.is_empty() {
+        bail!("Refusing to import, {}", conflicts.join(", "));
+    }
+
+    let mut changed = Vec::new();
+    for rsc in st.source_files.iter() {
+        let Some(blocks) = replacements.get(&rsc.file) else {
+            continue;
+        };
+        let mut lines = (0..rsc.total_lines)
+            .map(|n| {
+                rsc.get(&n)
+                    .map(|rl| rl.contents.clone())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<String>>();
+        let mut blocks = blocks.iter().collect::<Vec<_>>();
+        // Bottom up so the line numbers of those above stay put.
+        blocks.sort_by_key(|(block, _)| std::cmp::Reverse(block.lines[0]));
+        for (block, new) in blocks {
+            let start = block.lines[0];
+            lines.splice(start..start + block.lines.len(), new.iter().cloned());
+        }
+        changed.push((rsc.file.clone(), lines.join("\n") + "\n"));
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use std::fs;
+
+    #[test]
+    fn round_trips_docs() {
+        let dir = TestDir::new("export");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "//! The crate.",
+                "/// A thing.",
+                "///",
+                "/// With two paragraphs.",
+                "pub struct Thing;",
+                "impl Thing {",
+                "    /// Makes one.",
+                "    pub fn new() -> Self { Thing }",
+                "}",
+                "/// Another.",
+                "pub struct Other;",
+            ]
+            .join("\n"),
+        );
+
+        let src = dir.join("src");
+        let st = SourceTree::new_from_dir(src.display().to_string());
+        let exports = export_docs(&st, &src);
+        let names = exports
+            .iter()
+            .map(|e| (e.item.as_str(), e.file_name()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("crate::", PathBuf::from("index.md")),
+                ("crate::Thing", PathBuf::from("Thing.md")),
+                ("crate::Thing::new", PathBuf::from("Thing/new.md")),
+                ("crate::Other", PathBuf::from("Other.md")),
+            ]
+        );
+        let thing = DocExport::from_markdown(&exports[1].to_markdown()).unwrap();
+        assert_eq!(thing, exports[1]);
+        assert_eq!(thing.text, "A thing.\n\nWith two paragraphs.");
+
+        let mut new = exports[2].clone();
+        new.text = "Makes a [`Thing`].\n\nCheaply.".into();
+        let changed = import_docs(&st, &[thing.clone(), new.clone()], &src).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].1.contains(
+            "impl Thing {\n    /// Makes a [`Thing`].\n    ///\n    /// Cheaply.\n    pub fn new()"
+        ));
+
+        // Changed on both sides since.
+        let mut stale = thing.clone();
+        stale.hash = fnv_hash("something else");
+        stale.text = "A thing, edited.".into();
+        assert!(import_docs(&st, &[stale, new], &src).is_err());
+
+        // Other changed in the source, only Thing in the export.
+        let lib = fs::read_to_string(src.join("lib.rs")).unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            lib.replace("/// Another.", "/// Another one."),
+        )
+        .unwrap();
+        let st = SourceTree::new_from_dir(src.display().to_string());
+        let mut edited = thing;
+        edited.text = "A thing, edited.".into();
+        let changed = import_docs(&st, &[edited, exports[3].clone()], &src).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0]
+            .1
+            .starts_with("//! The crate.\n/// A thing, edited.\npub struct Thing;"));
+        assert!(changed[0].1.contains("/// Another one.\npub struct Other;"));
+    }
+}
@@ -0,0 +1,214 @@
+//!
+//! Linking of terms from the `[glossary]` of the config file, i.e `protobuf` to its spec or
+//! `Frame` to `crate::wire::Frame`, to wherever they're first mentioned in each doc block.
+//!
+use super::{
+    replace::protected_ranges,
+    rules::{Rule, RuleContext, SpanEdit},
+    utils::{Flavour, SourceTree},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+    path::PathBuf,
+};
+
+lazy_static! {
+    static ref WORD_CHAR: Regex = Regex::new(r"^\p{XID_Continue}$").unwrap();
+}
+
+/// A mention of a glossary term in a doc line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermMention<'a> {
+    pub term: &'a str,
+    /// The term itself, a plural's `s` excluded.
+    pub range: Range<usize>,
+    /// Already the text of a link.
+    pub linked: bool,
+}
+
+/// Terms and the URL, or intra-doc path, each links to.
+#[derive(Default, Debug, Clone)]
+pub struct Glossary {
+    pub terms: BTreeMap<String, String>,
+}
+
+impl Glossary {
+    pub fn new(terms: BTreeMap<String, String>) -> Self {
+        Self { terms }
+    }
+
+    /// The mentions of glossary terms in `contents`, longest terms first where they overlap and
+    /// those in code spans skipped.
+    pub fn mentions<'a>(&'a self, contents: &str) -> Vec<TermMention<'a>> {
+        let links = protected_ranges(contents, false);
+        let code = protected_ranges(contents, true)
+            .into_iter()
+            .filter(|r| !links.contains(r))
+            .collect::<Vec<Range<usize>>>();
+        let overlaps = |a: &Range<usize>, b: &Range<usize>| a.start < b.end && b.start < a.end;
+        let is_word = |c: Option<char>| {
+            c.map(|c| WORD_CHAR.is_match(c.encode_utf8(&mut [0; 4])))
+                .unwrap_or(false)
+        };
+
+        let mut terms = self.terms.keys().collect::<Vec<&String>>();
+        terms.sort_by_key(|t| std::cmp::Reverse(t.len()));
+
+        let mut mentions: Vec<TermMention> = Vec::new();
+        for term in terms {
+            for (start, _) in contents.match_indices(term.as_str()) {
+                let range = start..start + term.len();
+                let mut rest = contents[range.end..].chars();
+                let after = match rest.next() {
+                    Some('s') => rest.next(),
+                    c => c,
+                };
+                if is_word(contents[..start].chars().last())
+                    || is_word(after)
+                    || code.iter().any(|c| overlaps(c, &range))
+                    || mentions.iter().any(|m| overlaps(&m.range, &range))
+                {
+                    continue;
+                }
+                mentions.push(TermMention {
+                    term,
+                    linked: links.iter().any(|l| overlaps(l, &range)),
+                    range,
+                });
+            }
+        }
+        mentions.sort_by_key(|m| m.range.start);
+        mentions
+    }
+
+    /// Where each term is mentioned in the docs of `st`, linked or not, fenced code left out.
+    pub fn usage(&self, st: &SourceTree) -> BTreeMap<&str, Vec<(PathBuf, usize)>> {
+        let mut usage: BTreeMap<&str, Vec<(PathBuf, usize)>> = BTreeMap::new();
+        for rsc in st.source_files.iter() {
+            let fenced = rsc
+                .doc_blocks
+                .iter()
+                .flat_map(|block| block.fenced.iter())
+                .collect::<HashSet<&usize>>();
+            let mut lines = rsc
+                .m
+                .values()
+                .filter(|rl| rl.flavour == Flavour::RUST_DOCS && !fenced.contains(&rl.line_num))
+                .collect::<Vec<_>>();
+            lines.sort_by_key(|rl| rl.line_num);
+            for rl in lines {
+                for m in self.mentions(&rl.contents) {
+                    usage
+                        .entry(m.term)
+                        .or_default()
+                        .push((rsc.file.clone(), rl.line_num));
+                }
+            }
+        }
+        usage
+    }
+
+    /// The link a mention of `term`, written as `text`, becomes.
+    fn link(&self, term: &str, text: &str) -> String {
+        match self.terms[term].as_str() {
+            url if url.contains("://") => format!("[{}]({})", text, url),
+            path => format!("[`{}`]({})", text, path),
+        }
+    }
+}
+
+impl Rule for Glossary {
+    fn id(&self) -> &str {
+        "glossary"
+    }
+
+    /// Links only the first mention of each term in a block, one that's already linked counts.
+    fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit> {
+        let mut seen = HashSet::new();
+        let mut edits = Vec::new();
+
+        for rl in ctx.prose() {
+            for m in self.mentions(&rl.contents) {
+                if !seen.insert(m.term) || m.linked {
+                    continue;
+                }
+                edits.push(SpanEdit {
+                    line_num: rl.line_num,
+                    range: m.range.clone(),
+                    replacement: self.link(m.term, &rl.contents[m.range.clone()]),
+                    rule: self.id().into(),
+                    message: format!("linked {} to {}", m.term, self.terms[m.term]),
+                });
+            }
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::config::ConfigFile;
+    use crate::search::test_utils::TestDir;
+
+    #[test]
+    fn links_first_mentions() {
+        let dir = TestDir::new("glossary");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "/// Reads protobuf Frames, protobufs are in `protobuf` format.",
+                "/// Every protobuf message, not protobuffer, is a Frame.",
+                "pub fn helper() {}",
+                "/// A [protobuf](x) Frame then another protobuf.",
+                "pub fn other() {}",
+                "/// ```",
+                "/// let frame: Frame = protobuf::decode(bytes);",
+                "/// ```",
+                "/// Decodes a protobuf.",
+                "pub fn decode() {}",
+            ]
+            .join("\n"),
+        );
+
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [glossary]
+            protobuf = "https://protobuf.dev"
+            Frame = "crate::wire::Frame"
+            "#,
+        )
+        .unwrap();
+        let glossary = Glossary::new(file.glossary);
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(glossary.clone())];
+        let adjusted = rsc.make_adjustments(&[], &st.symbols, &rules, &Default::default());
+
+        assert_eq!(adjusted.len(), 3);
+        assert_eq!(
+            adjusted[0].contents,
+            "/// Reads [protobuf](https://protobuf.dev) [`Frame`](crate::wire::Frame)s, protobufs are in `protobuf` format."
+        );
+        assert_eq!(adjusted[1].line_num, 3);
+        assert_eq!(
+            adjusted[1].contents,
+            "/// A [protobuf](x) [`Frame`](crate::wire::Frame) then another protobuf."
+        );
+        // Fenced code is left alone, the first mention after it is linked.
+        assert_eq!(adjusted[2].line_num, 8);
+        assert_eq!(
+            adjusted[2].contents,
+            "/// Decodes a [protobuf](https://protobuf.dev)."
+        );
+
+        let usage = glossary.usage(&st);
+        let lines = |term| usage[term].iter().map(|(_, n)| *n).collect::<Vec<usize>>();
+        assert_eq!(lines("protobuf"), vec![0, 0, 1, 3, 3, 8]);
+        assert_eq!(lines("Frame"), vec![0, 1, 3]);
+    }
+}
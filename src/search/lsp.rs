@@ -0,0 +1,806 @@
+//!
+//! A language server, speaking LSP over stdio, so an editor can show what `stklr preview` would
+//! link as diagnostics, apply them as code actions, complete item names in doc links and jump to
+//! what a link points to. See <https://microsoft.github.io/language-server-protocol/>.
+//!
+use super::{
+    config::Config,
+    rules::{Rule, SpanEdit},
+    std_index::std_targets,
+    symbols::{display_path, Symbol},
+    utils::{Flavour, RawLine, RawSourceCode, SourceTree},
+    workspace::Workspace,
+};
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use log::debug;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+lazy_static! {
+    // A doc link, `[text]` optionally followed by its `(dest)`.
+    static ref DOC_LINK: Regex = Regex::new(r"\[(?P<text>[^\]]*)\](?:\((?P<dest>[^)]*)\))?").unwrap();
+    // The start of an item name being typed into a doc link, i.e ``[`Sour``.
+    static ref LINK_START: Regex = Regex::new(r"\[`(?P<typed>[\w:]*)$").unwrap();
+}
+
+/// Reads one message, `None` once the client has hung up.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let mut body = vec![0; length.context("Message without a Content-Length")?];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub fn write_message(output: &mut impl Write, msg: &Value) -> Result<()> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Answers the client on `input` until it says to exit.
+pub fn serve(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    cfg: Config,
+    rules: Vec<Box<dyn Rule>>,
+) -> Result<()> {
+    let mut server = Server::new(cfg, rules);
+    while let Some(msg) = read_message(input)? {
+        for reply in server.handle(&msg) {
+            write_message(output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// A crate's tree, kept up to date one changed file at a time.
+struct Index {
+    tree: SourceTree,
+}
+
+impl Index {
+    fn contains(&self, file: &Path) -> bool {
+        match &self.tree.root {
+            Some(root) => file.starts_with(root),
+            None => self.tree.source_files.iter().any(|rsc| rsc.file == file),
+        }
+    }
+
+    /// Re-reads `file` from `contents`, or from disk when it's `None`, dropping it if it's gone.
+    /// Returns the files whose suggestions may have changed, `file` and those whose docs mention
+    /// an item that changed in it.
+    fn update(&mut self, file: &Path, contents: Option<&str>) -> Vec<PathBuf> {
+        let rsc = match contents {
+            Some(contents) => Some(RawSourceCode::new_from_contents(file, contents)),
+            None if file.exists() => Some(RawSourceCode::new_from_file(file)),
+            None => None,
+        };
+        if rsc.is_none() && !self.tree.source_files.iter().any(|sf| sf.file == file) {
+            return Vec::new();
+        }
+        let changed = self.tree.replace_file(file, rsc);
+
+        let mut affected = vec![file.to_path_buf()];
+        affected.extend(
+            self.tree
+                .source_files
+                .iter()
+                .filter(|sf| sf.file != file)
+                .filter(|sf| {
+                    sf.m.values()
+                        .filter(|rl| rl.flavour == Flavour::RUST_DOCS)
+                        .any(|rl| {
+                            changed
+                                .iter()
+                                .any(|ident| rl.contents.contains(ident.as_str()))
+                        })
+                })
+                .map(|sf| sf.file.clone()),
+        );
+        affected
+    }
+}
+
+pub struct Server {
+    cfg: Config,
+    rules: Vec<Box<dyn Rule>>,
+    indexes: Vec<Index>,
+    /// The documents the client has open, their contents are its to say until they're closed.
+    open: BTreeMap<PathBuf, String>,
+    pub exited: bool,
+}
+
+impl Server {
+    pub fn new(cfg: Config, rules: Vec<Box<dyn Rule>>) -> Self {
+        Self {
+            cfg,
+            rules,
+            indexes: Vec::new(),
+            open: BTreeMap::new(),
+            exited: false,
+        }
+    }
+
+    /// The replies to `msg`, diagnostics published included.
+    pub fn handle(&mut self, msg: &Value) -> Vec<Value> {
+        let method = msg["method"].as_str().unwrap_or_default();
+        let params = &msg["params"];
+        let id = msg.get("id").cloned();
+        let file = params["textDocument"]["uri"].as_str().and_then(uri_to_path);
+
+        let result = match (method, &file) {
+            ("initialize", _) => {
+                let root = params["rootUri"]
+                    .as_str()
+                    .and_then(uri_to_path)
+                    .or_else(|| params["rootPath"].as_str().map(PathBuf::from))
+                    .or_else(|| std::env::current_dir().ok())
+                    .unwrap_or_default();
+                self.load(&root);
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "codeActionProvider": true,
+                        "completionProvider": { "triggerCharacters": ["`"] },
+                        "definitionProvider": true,
+                    },
+                    "serverInfo": { "name": "stklr", "version": env!("CARGO_PKG_VERSION") },
+                })
+            }
+            ("shutdown", _) => Value::Null,
+            ("exit", _) => {
+                self.exited = true;
+                return Vec::new();
+            }
+            ("textDocument/didOpen", Some(file)) => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.open.insert(file.clone(), text.to_string());
+                let affected = self.update(file, Some(text));
+                return self.publish_open(&affected);
+            }
+            ("textDocument/didChange", Some(file)) => {
+                // Sync is `Full`, the last change holds the whole document.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                    .unwrap_or_default();
+                self.open.insert(file.clone(), text.to_string());
+                let affected = self.update(file, Some(text));
+                return self.publish_open(&affected);
+            }
+            ("textDocument/didClose", Some(file)) => {
+                self.open.remove(file);
+                let affected = self.update(file, None);
+                let mut replies = vec![publish(file, Vec::new())];
+                replies.extend(self.publish_open(&affected));
+                return replies;
+            }
+            ("workspace/didChangeWatchedFiles", _) => {
+                let mut affected = Vec::new();
+                for change in params["changes"].as_array().into_iter().flatten() {
+                    let file = match change["uri"].as_str().and_then(uri_to_path) {
+                        Some(file) if !self.open.contains_key(&file) => file,
+                        _ => continue,
+                    };
+                    affected.extend(self.update(&file, None));
+                }
+                affected.sort();
+                affected.dedup();
+                return self.publish_open(&affected);
+            }
+            ("textDocument/codeAction", Some(file)) => self.code_actions(file, &params["range"]),
+            ("textDocument/completion", Some(file)) => self.completion(file, &params["position"]),
+            ("textDocument/definition", Some(file)) => self.definition(file, &params["position"]),
+            _ => {
+                return match id {
+                    Some(id) => vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("Unsupported method {}", method) },
+                    })],
+                    None => Vec::new(),
+                };
+            }
+        };
+        match id {
+            Some(id) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => Vec::new(),
+        }
+    }
+
+    /// Indexes every crate under `root`, or `root` itself when it isn't a cargo project.
+    fn load(&mut self, root: &Path) {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let mut trees = match Workspace::from_dir(&root, &self.cfg) {
+            Ok(ws) => ws.crates.into_iter().map(|c| c.tree).collect(),
+            Err(e) => {
+                debug!("Not a cargo project, {:?}", e);
+                let mut tree = SourceTree::new_from_dir(root.display().to_string());
+                tree.root = Some(root);
+                vec![tree]
+            }
+        };
+        let std = std_targets(self.cfg.no_std);
+        trees.iter_mut().for_each(|st| st.add_targets(&std));
+        self.indexes = trees.into_iter().map(|tree| Index { tree }).collect();
+    }
+
+    fn update(&mut self, file: &Path, contents: Option<&str>) -> Vec<PathBuf> {
+        // The deepest crate containing it, for a workspace's root package holds its members too.
+        let index = self
+            .indexes
+            .iter_mut()
+            .filter(|index| index.contains(file))
+            .max_by_key(|index| index.tree.root.as_ref().map(|r| r.components().count()));
+        match index {
+            Some(index) => index.update(file, contents),
+            None => Vec::new(),
+        }
+    }
+
+    fn find(&self, file: &Path) -> Option<(&SourceTree, &RawSourceCode)> {
+        self.indexes.iter().find_map(|index| {
+            let rsc = index
+                .tree
+                .source_files
+                .iter()
+                .find(|rsc| rsc.file == file)?;
+            Some((&index.tree, rsc))
+        })
+    }
+
+    /// What `stklr fix` would change in `file`, edit by edit.
+    fn suggestions(&self, file: &Path) -> Vec<SpanEdit> {
+        let Some((st, rsc)) = self.find(file) else {
+            return Vec::new();
+        };
+        rsc.make_adjustments(&rsc.named_idents, &st.symbols, &self.rules, &self.cfg)
+            .into_iter()
+            .flat_map(|adj| match rsc.get(&adj.line_num) {
+                Some(old) => adj.original_edits(&old.contents),
+                None => Vec::new(),
+            })
+            .collect()
+    }
+
+    fn diagnostic(&self, file: &Path, edit: &SpanEdit) -> Value {
+        json!({
+            "range": self.range(file, edit.line_num, edit.range.clone()),
+            "severity": 3,
+            "source": "stklr",
+            "code": edit.rule,
+            "message": format!("could be {} ({})", edit.replacement, edit.message),
+        })
+    }
+
+    /// The diagnostics of those of `files` the client has open.
+    fn publish_open(&self, files: &[PathBuf]) -> Vec<Value> {
+        files
+            .iter()
+            .filter(|file| self.open.contains_key(*file))
+            .map(|file| {
+                let diagnostics = self
+                    .suggestions(file)
+                    .iter()
+                    .map(|edit| self.diagnostic(file, edit))
+                    .collect();
+                publish(file, diagnostics)
+            })
+            .collect()
+    }
+
+    /// A fix for each suggestion on the lines of `range`, and one for all of the file's when
+    /// there's more than one.
+    fn code_actions(&self, file: &Path, range: &Value) -> Value {
+        let uri = path_to_uri(file);
+        let first = range["start"]["line"].as_u64().unwrap_or_default() as usize;
+        let last = range["end"]["line"].as_u64().unwrap_or(first as u64) as usize;
+        let suggestions = self.suggestions(file);
+        let text_edit = |edit: &SpanEdit| {
+            json!({
+                "range": self.range(file, edit.line_num, edit.range.clone()),
+                "newText": edit.replacement,
+            })
+        };
+
+        let mut actions = suggestions
+            .iter()
+            .filter(|edit| (first..=last).contains(&edit.line_num))
+            .map(|edit| {
+                json!({
+                    "title": format!("stklr: {}", edit.replacement),
+                    "kind": "quickfix",
+                    "diagnostics": [self.diagnostic(file, edit)],
+                    "edit": { "changes": { uri.clone(): [text_edit(edit)] } },
+                })
+            })
+            .collect::<Vec<Value>>();
+        if suggestions.len() > 1 {
+            actions.push(json!({
+                "title": format!("stklr: apply all {} suggestions in this file", suggestions.len()),
+                "kind": "source.fixAll.stklr",
+                "edit": { "changes": {
+                    uri: suggestions.iter().map(text_edit).collect::<Vec<Value>>()
+                } },
+            }));
+        }
+        Value::Array(actions)
+    }
+
+    /// The items linkable from `file`, after a ``[` `` in its docs.
+    fn completion(&self, file: &Path, position: &Value) -> Value {
+        let Some((st, rsc, line, col)) = self.position(file, position) else {
+            return Value::Null;
+        };
+        if line.flavour != Flavour::RUST_DOCS {
+            return Value::Null;
+        }
+        let Some(caps) = LINK_START.captures(&line.contents[..col]) else {
+            return Value::Null;
+        };
+        let typed = caps["typed"].rsplit("::").next().unwrap_or_default();
+
+        let mut items: BTreeMap<String, Value> = BTreeMap::new();
+        for sym in st.symbols.iter().filter(|sym| sym.ident.starts_with(typed)) {
+            items.entry(sym.ident.clone()).or_insert_with(|| {
+                json!({
+                    "label": sym.ident,
+                    "kind": completion_kind(&sym.flavour),
+                    "detail": display_path("crate", &sym.path),
+                })
+            });
+        }
+        for (ident, target) in rsc.link_targets.iter() {
+            if ident.starts_with(typed) {
+                items.entry(ident.clone()).or_insert_with(
+                    || json!({ "label": ident, "kind": 1, "detail": target.dest() }),
+                );
+            }
+        }
+        json!({ "isIncomplete": false, "items": items.into_values().collect::<Vec<Value>>() })
+    }
+
+    /// Where the item an intra-doc link under `position` points to is declared.
+    fn definition(&self, file: &Path, position: &Value) -> Value {
+        let Some((st, _, line, col)) = self.position(file, position) else {
+            return Value::Null;
+        };
+        let Some(caps) = DOC_LINK
+            .captures_iter(&line.contents)
+            .find(|caps| caps.get(0).map(|m| m.start() <= col && col <= m.end()) == Some(true))
+        else {
+            return Value::Null;
+        };
+        let path = match caps.name("dest").map(|m| m.as_str().trim()) {
+            Some(dest) if !dest.is_empty() && !dest.contains("://") => dest,
+            _ => &caps["text"],
+        };
+        // `struct@Foo`, `Foo()` and `foo!` all name plain `Foo` and `foo`.
+        let path = path.trim_matches('`');
+        let path = path.split_once('@').map(|(_, p)| p).unwrap_or(path);
+        let path = path.trim_end_matches("()").trim_end_matches('!');
+        let segments = path
+            .split("::")
+            .filter(|s| !matches!(*s, "crate" | "self" | "super"))
+            .collect::<Vec<&str>>();
+        let Some(name) = segments.last() else {
+            return Value::Null;
+        };
+
+        let written = segments
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        // Of the items sharing its name, the one the link's path fits best.
+        let matches = |st: &SourceTree| -> Option<Symbol> {
+            st.symbols
+                .iter()
+                .filter(|sym| sym.ident.trim_end_matches('!') == *name)
+                .min_by_key(|sym| !sym.path.ends_with(&written))
+                .cloned()
+        };
+        // Items of another crate of the workspace are named by its name first.
+        let sym = matches(st).or_else(|| {
+            self.indexes
+                .iter()
+                .map(|index| &index.tree)
+                .filter(|other| {
+                    other
+                        .crate_name
+                        .as_ref()
+                        .map(|n| n.replace('-', "_"))
+                        .as_deref()
+                        == path.split("::").next()
+                })
+                .find_map(matches)
+        });
+        let Some(sym) = sym else {
+            return Value::Null;
+        };
+        let start = self
+            .find(&sym.source_file)
+            .and_then(|(_, rsc)| rsc.get(&sym.line_num))
+            .and_then(|rl| rl.contents.find(name))
+            .unwrap_or_default();
+        json!({
+            "uri": path_to_uri(&sym.source_file),
+            "range": self.range(&sym.source_file, sym.line_num, start..start + name.len()),
+        })
+    }
+
+    /// The line `position` is on and how far into it, in bytes.
+    fn position(
+        &self,
+        file: &Path,
+        position: &Value,
+    ) -> Option<(&SourceTree, &RawSourceCode, &RawLine, usize)> {
+        let (st, rsc) = self.find(file)?;
+        let line = rsc.get(&(position["line"].as_u64()? as usize))?;
+        let col = byte_col(&line.contents, position["character"].as_u64()? as usize);
+        Some((st, rsc, line, col))
+    }
+
+    /// An LSP range on line `line_num` of `file`, whose columns count UTF-16 code units.
+    fn range(&self, file: &Path, line_num: usize, bytes: std::ops::Range<usize>) -> Value {
+        let line = self
+            .find(file)
+            .and_then(|(_, rsc)| rsc.get(&line_num))
+            .map(|rl| rl.contents.as_str())
+            .unwrap_or_default();
+        let col = |byte: usize| {
+            line.get(..byte)
+                .map(|s| s.encode_utf16().count())
+                .unwrap_or(byte)
+        };
+        json!({
+            "start": { "line": line_num, "character": col(bytes.start) },
+            "end": { "line": line_num, "character": col(bytes.end) },
+        })
+    }
+}
+
+fn publish(file: &Path, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": path_to_uri(file), "diagnostics": diagnostics },
+    })
+}
+
+/// The `CompletionItemKind` of an item.
+fn completion_kind(flavour: &Flavour) -> u8 {
+    match flavour {
+        Flavour::RUST_FN | Flavour::RUST_MACRO => 3,
+        Flavour::RUST_TY => 7,
+        Flavour::RUST_TRAIT => 8,
+        Flavour::RUST_MOD => 9,
+        Flavour::RUST_STATIC => 6,
+        Flavour::RUST_ENUM => 13,
+        Flavour::RUST_CONST => 21,
+        Flavour::RUST_STRUCT | Flavour::RUST_UNION => 22,
+        _ => 1,
+    }
+}
+
+/// The byte offset of UTF-16 column `col` of `line`.
+fn byte_col(line: &str, col: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= col {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match (
+            b,
+            tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok()),
+        ) {
+            (b'%', Some(hex)) if u8::from_str_radix(hex, 16).is_ok() => {
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // Windows paths come as `file:///C:/x.rs`, or `file:///c%3A/x.rs`.
+    let path = match path.strip_prefix('/') {
+        Some(rest) if has_drive(rest) => PathBuf::from(rest),
+        _ => PathBuf::from(path),
+    };
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut path = path.display().to_string();
+    // `C:\x.rs` is `file:///C:/x.rs`, canonical paths on Windows start with `\\?\` too.
+    if let Some(rest) = path.strip_prefix(r"\\?\") {
+        path = rest.to_string();
+    }
+    let mut uri = String::from("file://");
+    if has_drive(&path) {
+        uri.push('/');
+        uri.push_str(&path[..2]);
+        path = path[2..].replace('\\', "/");
+    }
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+/// Does `path` start with a Windows drive, i.e `C:`?
+fn has_drive(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use crate::search::{config::DepLinks, rules::builtin_rules};
+
+    /// A crate with a couple of files, and a client talking to a [`Server`] for it.
+    struct Client {
+        dir: TestDir,
+        server: Server,
+        uri: String,
+        wire_uri: String,
+    }
+
+    /// What's opened of `src/lib.rs`, unsaved changes declaring an `Other` that's linkable.
+    const OPENED: &str = "pub struct Thing;\npub struct Other;\n/// Makes a Thing from an Other.\npub fn make() {}\n/// See [`Thing`] or [`Ot";
+
+    impl Client {
+        fn new(name: &str) -> Self {
+            let dir = TestDir::new(name);
+            dir.write(
+                "Cargo.toml",
+                "[package]\nname = \"served\"\nversion = \"0.1.0\"\n",
+            );
+            let lib = dir.write("src/lib.rs", "pub struct Thing;\n");
+            let wire = dir.write("src/wire.rs", "/// Sends a Frame.\npub fn send() {}\n");
+            let cfg = Config {
+                dep_links: DepLinks::Skip,
+                ..Default::default()
+            };
+            Client {
+                uri: path_to_uri(&lib),
+                wire_uri: path_to_uri(&wire),
+                server: Server::new(cfg, builtin_rules()),
+                dir,
+            }
+        }
+
+        /// Initialized, with `src/lib.rs` opened as [`OPENED`].
+        fn opened(name: &str) -> Self {
+            let mut client = Self::new(name);
+            client.initialize();
+            let uri = client.uri.clone();
+            client.open(&uri, OPENED);
+            client
+        }
+
+        fn send(&mut self, id: Option<u64>, method: &str, params: Value) -> Vec<Value> {
+            let mut msg = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+            if let Some(id) = id {
+                msg["id"] = json!(id);
+            }
+            // Through the wire format and back, as a client would send it.
+            let mut wire = Vec::new();
+            write_message(&mut wire, &msg).unwrap();
+            let msg = read_message(&mut wire.as_slice()).unwrap().unwrap();
+            self.server.handle(&msg)
+        }
+
+        fn initialize(&mut self) -> Vec<Value> {
+            let root = path_to_uri(self.dir.path());
+            self.send(Some(1), "initialize", json!({ "rootUri": root }))
+        }
+
+        fn open(&mut self, uri: &str, text: &str) -> Vec<Value> {
+            self.send(
+                None,
+                "textDocument/didOpen",
+                json!({ "textDocument": { "uri": uri, "languageId": "rust", "version": 1, "text": text } }),
+            )
+        }
+
+        fn change(&mut self, text: &str) -> Vec<Value> {
+            let uri = self.uri.clone();
+            self.send(
+                None,
+                "textDocument/didChange",
+                json!({ "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [{ "text": text }] }),
+            )
+        }
+    }
+
+    fn position(line: u64, character: u64) -> Value {
+        json!({ "line": line, "character": character })
+    }
+
+    #[test]
+    fn initializes() {
+        let mut client = Client::new("lsp_init");
+        let init = client.initialize();
+        assert_eq!(
+            init[0]["result"]["capabilities"]["definitionProvider"],
+            true
+        );
+    }
+
+    #[test]
+    fn publishes_diagnostics() {
+        let mut client = Client::new("lsp_diagnostics");
+        client.initialize();
+        // Unsaved changes are what's linked, and what's declared in them is linkable.
+        let uri = client.uri.clone();
+        let published = client.open(&uri, OPENED);
+
+        let diagnostics = &published[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({ "start": position(2, 12), "end": position(2, 17) })
+        );
+        assert_eq!(
+            diagnostics[1]["range"],
+            json!({ "start": position(2, 26), "end": position(2, 31) })
+        );
+    }
+
+    #[test]
+    fn offers_code_actions() {
+        let mut client = Client::opened("lsp_actions");
+        let uri = client.uri.clone();
+        let actions = client.send(
+            Some(2),
+            "textDocument/codeAction",
+            json!({ "textDocument": { "uri": uri }, "range": { "start": position(2, 0), "end": position(2, 0) }, "context": { "diagnostics": [] } }),
+        );
+        assert_eq!(
+            actions[0]["result"][0]["edit"]["changes"][&uri][0]["newText"],
+            "[`Thing`]"
+        );
+        assert_eq!(actions[0]["result"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn completes_links() {
+        let mut client = Client::opened("lsp_completion");
+        let uri = client.uri.clone();
+        let completion = client.send(
+            Some(3),
+            "textDocument/completion",
+            json!({ "textDocument": { "uri": uri }, "position": position(4, 24) }),
+        );
+        let labels = completion[0]["result"]["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert!(labels.contains(&"Other") && !labels.contains(&"Thing"));
+    }
+
+    #[test]
+    fn goes_to_definition() {
+        let mut client = Client::opened("lsp_definition");
+        let uri = client.uri.clone();
+        let definition = client.send(
+            Some(4),
+            "textDocument/definition",
+            json!({ "textDocument": { "uri": uri }, "position": position(4, 12) }),
+        );
+        assert_eq!(
+            definition[0]["result"],
+            json!({ "uri": uri, "range": { "start": position(0, 11), "end": position(0, 16) } })
+        );
+    }
+
+    #[test]
+    fn republishes_only_what_changed() {
+        let mut client = Client::opened("lsp_republish");
+        let (uri, wire_uri) = (client.uri.clone(), client.wire_uri.clone());
+        let published = client.open(&wire_uri, "/// Sends a Frame.\npub fn send() {}\n");
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+
+        // The changed file, and those mentioning what changed in it.
+        let published = client.change("pub struct Thing;\npub struct Frame;\n");
+        let uris = published
+            .iter()
+            .map(|p| p["params"]["uri"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(uris, vec![uri.as_str(), wire_uri.as_str()]);
+        assert!(published[1]["params"]["diagnostics"][0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("could be [`Frame`](crate::Frame) "));
+
+        // Nothing linkable changed, so only the file itself.
+        let published = client.change("/// A thing.\npub struct Thing;\npub struct Frame;\n");
+        assert_eq!(published.len(), 1);
+    }
+
+    #[test]
+    fn rejects_unknown_methods() {
+        let mut client = Client::new("lsp_unknown");
+        client.initialize();
+        let unknown = client.send(Some(5), "textDocument/hover", json!({}));
+        assert_eq!(unknown[0]["error"]["code"], -32601);
+        client.send(None, "exit", Value::Null);
+        assert!(client.server.exited);
+    }
+
+    #[test]
+    fn converts_uris() {
+        let cases = [
+            ("file:///tmp/a%20b/x.rs", "/tmp/a b/x.rs"),
+            ("file:///C:/crate/x.rs", "C:/crate/x.rs"),
+            ("file:///c%3A/crate/x.rs", "c:/crate/x.rs"),
+        ];
+        for (uri, path) in cases {
+            assert_eq!(uri_to_path(uri), Some(PathBuf::from(path)));
+        }
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+
+        assert_eq!(
+            path_to_uri(Path::new("/tmp/a b/x.rs")),
+            "file:///tmp/a%20b/x.rs"
+        );
+        assert_eq!(
+            path_to_uri(Path::new(r"C:\crate\x.rs")),
+            "file:///C:/crate/x.rs"
+        );
+        assert_eq!(
+            path_to_uri(Path::new(r"\\?\C:\crate\x.rs")),
+            "file:///C:/crate/x.rs"
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//!
+//! Linking of crate items mentioned in markdown files, the README, CHANGELOG and `docs/**/*.md`.
+//! Intra-doc links don't work outside of rust-doc, so they're linked to the item's page instead,
+//! unless the file is included as docs with `#![doc = include_str!("../README.md")]`.
+//!
+use super::{
+    confidence::{self, WordUsage},
+    config::{Config, LinkPolicy, MdLinks},
+    consts::{NEVERS, RUST_CODE_SPAN, RUST_DOC_INCLUDE},
+    deps::LINKED_FLAVOURS,
+    migrate::PAGE_FLAVOURS,
+    replace::protected_ranges,
+    symbols::Symbol,
+    ticks::tick_code_tokens,
+    utils::{find_mentions, SourceTree},
+};
+
+use anyhow::{Context, Result};
+use glob::glob;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+lazy_static! {
+    static ref TOKEN: Regex = Regex::new(r"\S+").unwrap();
+    static ref BACKTICKS: Regex = Regex::new(r"`+").unwrap();
+}
+
+/// A markdown file of a crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownFile {
+    pub file: PathBuf,
+    pub lines: Vec<String>,
+    /// Included as docs somewhere with `include_str!`, so it gets intra-doc links like any docs.
+    pub included: bool,
+}
+
+impl MarkdownFile {
+    pub fn read(file: &Path, included: bool) -> Result<Self> {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("Unable to read {}", file.display()))?;
+        Ok(Self {
+            file: file.to_path_buf(),
+            lines: text.lines().map(|l| l.to_string()).collect(),
+            included,
+        })
+    }
+}
+
+/// Where a link to an item of a crate goes from one of its markdown files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MdTarget {
+    dest: String,
+    /// An intra-doc path, which a mention's `::member` or `()` can be added to.
+    intra: bool,
+    /// Plain words can be linked to it, not just code spans, see [`LINKED_FLAVOURS`].
+    plain: bool,
+}
+
+/// The markdown files of the crate `st`, its README, CHANGELOG, `docs/**/*.md` and any included as
+/// docs. Only crates found through their `Cargo.toml` have any.
+pub fn find_markdown(st: &SourceTree) -> Vec<MarkdownFile> {
+    let root = match &st.root {
+        Some(root) => root,
+        None => return Vec::new(),
+    };
+    let included = st
+        .source_files
+        .iter()
+        .flat_map(|rsc| {
+            let dir = rsc.file.parent().map(Path::to_path_buf).unwrap_or_default();
+            rsc.m
+                .values()
+                .filter_map(|rl| RUST_DOC_INCLUDE.captures(&rl.contents))
+                .map(move |caps| dir.join(&caps["path"]))
+        })
+        .filter_map(|p| p.canonicalize().ok())
+        .collect::<HashSet<PathBuf>>();
+
+    let mut files = vec![root.join("README.md"), root.join("CHANGELOG.md")];
+    files.extend(
+        glob(&format!("{}/docs/**/*.md", root.display()))
+            .unwrap()
+            .filter_map(Result::ok),
+    );
+    files.extend(included.iter().cloned());
+    let mut files = files
+        .into_iter()
+        .filter_map(|f| f.canonicalize().ok())
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+    files.dedup();
+
+    files
+        .iter()
+        .filter_map(|f| MarkdownFile::read(f, included.contains(f)).ok())
+        .collect()
+}
+
+/// The lines of `md` with mentions of the items of `st` linked, those changed at least. Code spans
+/// naming an item are always linked, plain words as [`Config::mentions`] says.
+pub fn link_markdown(md: &MarkdownFile, st: &SourceTree, cfg: &Config) -> Vec<(usize, String)> {
+    let base = match md.included {
+        true => None,
+        false => match docs_base(st, cfg, md.file.parent()) {
+            Some(base) => Some(base),
+            None => return Vec::new(),
+        },
+    };
+    link_lines(&md.lines, &md_targets(st, base.as_deref()), st, cfg, false)
+}
+
+/// `text` with mentions of the items of `st` linked to their pages under `base`, the URL of the
+/// crate's docs, i.e `https://docs.rs/my-crate/latest/my_crate`, and code-ish tokens backticked
+/// like they are in doc comments.
+pub fn link_text(text: &str, st: &SourceTree, cfg: &Config, base: &str) -> String {
+    let mut lines = text.lines().map(String::from).collect::<Vec<String>>();
+    for (n, linked) in link_lines(&lines, &md_targets(st, Some(base)), st, cfg, true) {
+        lines[n] = linked;
+    }
+    let mut linked = lines.join("\n");
+    if text.ends_with('\n') {
+        linked.push('\n');
+    }
+    linked
+}
+
+/// Where the docs of `st` are, as [`Config::md_links`] says, from the markdown in the directory
+/// `from`.
+pub fn docs_base(st: &SourceTree, cfg: &Config, from: Option<&Path>) -> Option<String> {
+    let krate = st.crate_name.clone()?;
+    let crate_dir = krate.replace('-', "_");
+    match cfg.md_links {
+        MdLinks::Skip => None,
+        MdLinks::DocsRs => Some(format!("https://docs.rs/{}/latest/{}", krate, crate_dir)),
+        MdLinks::Relative => {
+            let root = st.root.as_ref()?;
+            let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+            Some(relative(from?, &doc_dir(&root).join(&crate_dir)))
+        }
+    }
+}
+
+/// The lines changed by linking mentions to `targets`, and backticking code-ish tokens as
+/// [`super::ticks`] does doc comments when `tick` is set.
+fn link_lines(
+    lines: &[String],
+    targets: &HashMap<String, MdTarget>,
+    st: &SourceTree,
+    cfg: &Config,
+    tick: bool,
+) -> Vec<(usize, String)> {
+    let usage = st
+        .source_files
+        .first()
+        .map(|sf| sf.word_usage.clone())
+        .unwrap_or_default();
+    let mut seen = HashSet::new();
+    let mut fenced = false;
+    let mut changed = Vec::new();
+
+    for (n, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+            continue;
+        }
+        if trimmed.is_empty() && cfg.link_policy == LinkPolicy::FirstPerParagraph {
+            seen.clear();
+        }
+        // Links in headings just get in the way of them.
+        if fenced || trimmed.starts_with('#') {
+            continue;
+        }
+        let linked = match tick {
+            true => tick_prose(line),
+            false => line.to_owned(),
+        };
+        let linked = link_line(&linked, targets, cfg, &mut seen, &usage);
+        if linked != *line {
+            changed.push((n, linked));
+        }
+    }
+    changed
+}
+
+/// How each item of `st` that has a page is linked, by ident, to its page under `base` or by
+/// intra-doc path when there's no base, i.e from markdown included as docs. Idents naming more
+/// than one item aren't linked at all, nor are those in [`NEVERS`].
+fn md_targets(st: &SourceTree, base: Option<&str>) -> HashMap<String, MdTarget> {
+    let mut found: HashMap<String, Vec<MdTarget>> = HashMap::new();
+    for sym in st.symbols.iter().filter(|sym| !sym.associated) {
+        // Modules too often share their name with what they're about, i.e `docs`.
+        if !PAGE_FLAVOURS.contains(&sym.flavour) || NEVERS.contains(&sym.ident.as_str()) {
+            continue;
+        }
+        let target = match base {
+            None => sym.link_target().map(|dest| (dest, true)),
+            Some(base) => page(sym).map(|page| (format!("{}/{}", base, page), false)),
+        };
+        if let Some((dest, intra)) = target {
+            let targets = found.entry(sym.ident.clone()).or_default();
+            if !targets.iter().any(|t| t.dest == dest) {
+                targets.push(MdTarget {
+                    dest,
+                    intra,
+                    plain: LINKED_FLAVOURS.contains(&sym.flavour),
+                });
+            }
+        }
+    }
+    found
+        .into_iter()
+        .filter_map(|(ident, mut targets)| match targets.len() {
+            1 => Some((ident, targets.pop()?)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The page rust-doc gives `sym`, relative to its crate's, i.e `search/utils/struct.SourceTree.html`.
+fn page(sym: &Symbol) -> Option<String> {
+    let (name, dirs) = sym.canonical()?.split_last()?;
+    let file = format!(
+        "{}.{}.html",
+        sym.flavour.html_kind()?,
+        name.trim_end_matches('!')
+    );
+    Some(
+        dirs.iter()
+            .cloned()
+            .chain(std::iter::once(file))
+            .collect::<Vec<String>>()
+            .join("/"),
+    )
+}
+
+/// Where `cargo doc` writes the docs of the crate at `root`, the `target/doc` of the workspace it's
+/// in, which is where the `Cargo.lock` is.
+fn doc_dir(root: &Path) -> PathBuf {
+    root.ancestors()
+        .find(|dir| dir.join("Cargo.lock").exists())
+        .unwrap_or(root)
+        .join("target/doc")
+}
+
+/// The relative path from the directory `from` to `to`, with `/`s as it's for a URL.
+fn relative(from: &Path, to: &Path) -> String {
+    let from = from.components().collect::<Vec<Component>>();
+    let to = to.components().collect::<Vec<Component>>();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(
+            to[common..]
+                .iter()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+        )
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// `line` with the code-ish tokens of its prose backticked, links, code spans and indented code
+/// left as they are. Primitives aren't linked, their pages are only reachable by intra-doc link.
+fn tick_prose(line: &str) -> String {
+    if line.starts_with("    ") || line.starts_with('\t') {
+        return line.to_owned();
+    }
+    let mut protected = protected_ranges(line, true);
+    protected.sort_by_key(|r| r.start);
+    let mut out = String::new();
+    let mut last = 0;
+    for r in protected {
+        // Inside one already kept, a code span in a link's text say.
+        if r.start < last {
+            continue;
+        }
+        out.push_str(&tick_code_tokens(&line[last..r.start], false));
+        out.push_str(&line[r.clone()]);
+        last = r.end;
+    }
+    out.push_str(&tick_code_tokens(&line[last..], false));
+    out
+}
+
+/// The code spans of a markdown `line`, backticks included, which can open with more than one
+/// backtick so as to hold some, i.e ``` `` `Foo` `` ```.
+fn code_spans(line: &str) -> Vec<Range<usize>> {
+    let runs = BACKTICKS
+        .find_iter(line)
+        .map(|m| m.range())
+        .collect::<Vec<Range<usize>>>();
+    let mut spans = Vec::new();
+    let mut n = 0;
+    while n < runs.len() {
+        let open = &runs[n];
+        match runs[n + 1..].iter().position(|r| r.len() == open.len()) {
+            Some(close) => {
+                spans.push(open.start..runs[n + 1 + close].end);
+                n += close + 2;
+            }
+            None => n += 1,
+        }
+    }
+    spans
+}
+
+/// Links the mentions in one line of markdown, `seen` being what's already been linked for
+/// [`LinkPolicy`]'s sake.
+fn link_line(
+    line: &str,
+    targets: &HashMap<String, MdTarget>,
+    cfg: &Config,
+    seen: &mut HashSet<String>,
+    usage: &WordUsage,
+) -> String {
+    let tokens = TOKEN.find_iter(line).collect::<Vec<regex::Match>>();
+    let spans = code_spans(line);
+    let mut protected = protected_ranges(line, false);
+    // Indented code.
+    if line.starts_with("    ") || line.starts_with('\t') {
+        protected.push(0..line.len());
+    }
+    let mut allowed =
+        |ident: &str| seen.insert(ident.to_string()) || cfg.link_policy == LinkPolicy::All;
+    let mut out = String::new();
+    let mut last = 0;
+
+    for (n, token) in tokens.iter().enumerate() {
+        let word = token.as_str();
+        // Part of a code span that isn't just this word, i.e ``` `` `Foo` `` ``` or `a Foo`.
+        if spans.iter().any(|s| {
+            s.start < token.end()
+                && token.start() < s.end
+                && !(token.start() <= s.start && s.end <= token.end())
+        }) {
+            continue;
+        }
+        // Already a link, autolink or reference definition, or indented code.
+        if let Some(r) = protected
+            .iter()
+            .find(|r| r.start < token.end() && token.start() < r.end)
+        {
+            if line[r.clone()].starts_with('[') {
+                for ident in targets
+                    .keys()
+                    .filter(|i| word.contains(i.trim_end_matches('!')))
+                {
+                    allowed(ident);
+                }
+            }
+            continue;
+        }
+
+        let linked = if word.contains('`') {
+            RUST_CODE_SPAN
+                .replace_all(word, |caps: &regex::Captures| {
+                    let code = &caps["code"];
+                    match targets.get(code.trim_end_matches("()")) {
+                        Some(t)
+                            if caps["open"].is_empty() && allowed(code.trim_end_matches("()")) =>
+                        {
+                            format!("[`{}`]({})", code, t.dest)
+                        }
+                        _ => caps[0].to_string(),
+                    }
+                })
+                .into_owned()
+        } else if cfg.mentions.plain() {
+            let before = n.checked_sub(1).map(|b| tokens[b].as_str());
+            let after = tokens.get(n + 1).map(|a| a.as_str());
+            let mut mentions = targets
+                .iter()
+                .filter(|(_, t)| t.plain)
+                .flat_map(|(ident, t)| {
+                    find_mentions(word, ident)
+                        .into_iter()
+                        .map(move |m| (ident, t, m))
+                })
+                .filter(|(ident, t, m)| {
+                    let written = &word[m.range.clone()];
+                    (t.intra || !m.suffix.contains("::"))
+                        && confidence::score(
+                            ident.trim_end_matches('!'),
+                            written,
+                            before,
+                            after,
+                            usage,
+                        ) >= cfg.min_confidence
+                })
+                .collect::<Vec<_>>();
+            mentions.sort_by_key(|(_, _, m)| std::cmp::Reverse(m.range.start));
+
+            let mut linked = word.to_string();
+            let mut free_until = word.len();
+            for (ident, t, m) in mentions {
+                if m.range.end > free_until || !allowed(ident) {
+                    continue;
+                }
+                let dest = match t.intra {
+                    true => format!("{}{}", t.dest, m.suffix),
+                    false => t.dest.clone(),
+                };
+                linked.replace_range(m.range.clone(), &format!("[`{}`]({})", m.text, dest));
+                free_until = m.range.start;
+            }
+            linked
+        } else {
+            continue;
+        };
+
+        if linked != word {
+            out.push_str(&line[last..token.start()]);
+            out.push_str(&linked);
+            last = token.end();
+        }
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use crate::search::workspace::Workspace;
+
+    #[test]
+    fn links_markdown() {
+        let dir = TestDir::new("markdown");
+        dir.write(
+            "Cargo.toml",
+            "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\n",
+        );
+        dir.write(
+            "src/lib.rs",
+            "#![doc = include_str!(\"../README.md\")]\npub mod wire;\npub struct Thing;\npub fn run() {}\n",
+        );
+        dir.write("src/wire.rs", "pub struct Frame;\n");
+        dir.write(
+            "README.md",
+            "# Thing\nMake a Thing, then `run()`, not `` `Thing` ``.\n```\nThing\n```\n",
+        );
+        dir.write(
+            "docs/guide.md",
+            "# Guide\nA Thing holds a `Frame`, not [Thing](x).\n",
+        );
+        dir.write(
+            "docs/links.md",
+            &[
+                "See [the Thing type](https://example.com/Thing) or <https://example.com/Thing>.",
+                "[Thing]: https://example.com/Thing",
+                "",
+                "    let thing = Thing;",
+            ]
+            .join("\n"),
+        );
+
+        let ws = Workspace::from_dir(dir.path(), &Config::default()).unwrap();
+        let st = ws.trees().next().unwrap();
+        let md = find_markdown(st);
+        assert_eq!(md.len(), 3);
+        let file = |name| md.iter().find(|md| md.file.ends_with(name)).unwrap();
+
+        let guide = file("guide.md");
+        assert_eq!(
+            link_markdown(guide, st, &Config::default()),
+            vec![(
+                1,
+                "A [`Thing`](https://docs.rs/my-crate/latest/my_crate/struct.Thing.html) holds a \
+                 [`Frame`](https://docs.rs/my-crate/latest/my_crate/wire/struct.Frame.html), not [Thing](x)."
+                    .to_string()
+            )]
+        );
+        let cfg = Config {
+            md_links: MdLinks::Relative,
+            ..Default::default()
+        };
+        assert!(link_markdown(guide, st, &cfg)[0]
+            .1
+            .starts_with("A [`Thing`](../target/doc/my_crate/struct.Thing.html)"));
+
+        let cfg = Config {
+            link_policy: LinkPolicy::All,
+            ..Default::default()
+        };
+        assert_eq!(link_markdown(file("links.md"), st, &cfg), vec![]);
+
+        let readme = file("README.md");
+        assert_eq!(
+            link_markdown(readme, st, &Config::default()),
+            vec![(
+                1,
+                "Make a [`Thing`](crate::Thing), then [`run()`](crate::run), not `` `Thing` ``."
+                    .to_string()
+            )]
+        );
+    }
+}
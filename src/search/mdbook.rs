@@ -0,0 +1,177 @@
+//!
+//! mdBook preprocessor support, linking mentions of crate items in a book's chapters to the crate's
+//! API docs. mdBook hands a preprocessor `[context, book]` as JSON on stdin and takes the book back
+//! on stdout, see <https://rust-lang.github.io/mdBook/for_developers/preprocessors.html>.
+//!
+use super::{
+    config::{Config, DepLinks},
+    markdown::{docs_base, link_text},
+    utils::SourceTree,
+    workspace::Workspace,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// The `[preprocessor.stklr]` table of `book.toml`.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BookSettings {
+    /// The directory of the crate (or workspace) the book is about, relative to the book's, by
+    /// default the nearest one up that has a `Cargo.toml`.
+    #[serde(rename = "crate")]
+    pub krate: Option<PathBuf>,
+    /// Where the API docs are published, the directory holding one per crate, i.e `../api` when
+    /// they're published alongside the book. By default where `--md-links` says.
+    pub api_url: Option<String>,
+}
+
+/// Does the preprocessor support `renderer`? Links are plain markdown, so every one is.
+pub fn supports(_renderer: &str) -> bool {
+    true
+}
+
+/// Takes the `[context, book]` mdBook hands a preprocessor, returning the book with the items of
+/// the crate it's about linked in every chapter.
+pub fn preprocess(input: &str, cfg: &Config) -> Result<String> {
+    let (ctx, mut book): (Value, Value) =
+        serde_json::from_str(input).context("Unable to parse the book mdBook passed in")?;
+    let root = PathBuf::from(ctx["root"].as_str().unwrap_or("."));
+    let settings = match ctx.pointer("/config/preprocessor/stklr") {
+        Some(table) => BookSettings::deserialize(table)
+            .context("Unable to parse [preprocessor.stklr] of book.toml")?,
+        None => BookSettings::default(),
+    };
+
+    let dir = match &settings.krate {
+        Some(dir) => {
+            let dir = root.join(dir);
+            dir.canonicalize().unwrap_or(dir)
+        }
+        None => root
+            .ancestors()
+            .find(|dir| dir.join("Cargo.toml").exists())
+            .map(Path::to_path_buf)
+            .with_context(|| format!("No Cargo.toml in or above {}", root.display()))?,
+    };
+    // Only the crate's own items are linked from the book, so there's no need to index dependencies.
+    let cfg = &Config {
+        dep_links: DepLinks::Skip,
+        ..cfg.clone()
+    };
+    let trees = Workspace::from_dir(&dir, cfg)?
+        .crates
+        .into_iter()
+        .map(|c| c.tree)
+        .collect::<Vec<SourceTree>>();
+    let chapters = root.join("src");
+    let linked = trees
+        .iter()
+        .filter_map(|st| {
+            let base = match &settings.api_url {
+                Some(url) => Some(format!(
+                    "{}/{}",
+                    url.trim_end_matches('/'),
+                    st.crate_name.as_ref()?.replace('-', "_")
+                )),
+                None => docs_base(st, cfg, Some(&chapters)),
+            };
+            Some((st, base?))
+        })
+        .collect::<Vec<(&SourceTree, String)>>();
+
+    // `items` since mdBook 0.5, `sections` before.
+    for key in ["items", "sections"] {
+        if let Some(items) = book.get_mut(key) {
+            link_chapters(items, &linked, cfg);
+        }
+    }
+    Ok(serde_json::to_string(&book)?)
+}
+
+/// Links the content of each `Chapter` in `items`, and their sub chapters.
+fn link_chapters(items: &mut Value, linked: &[(&SourceTree, String)], cfg: &Config) {
+    for item in items.as_array_mut().into_iter().flatten() {
+        let chapter = match item.get_mut("Chapter") {
+            Some(chapter) => chapter,
+            None => continue,
+        };
+        if let Some(content) = chapter.get("content").and_then(Value::as_str) {
+            let mut content = content.to_string();
+            for (st, base) in linked {
+                content = link_text(&content, st, cfg, base);
+            }
+            chapter["content"] = Value::String(content);
+        }
+        if let Some(sub_items) = chapter.get_mut("sub_items") {
+            link_chapters(sub_items, linked, cfg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+
+    #[test]
+    fn links_chapters() {
+        let dir = TestDir::new("mdbook");
+        dir.write(
+            "Cargo.toml",
+            "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\n",
+        );
+        dir.write("src/lib.rs", "pub mod wire;\npub struct Thing;\n");
+        dir.write("src/wire.rs", "pub struct Frame;\n");
+        dir.write("guide/src/SUMMARY.md", "- [Intro](intro.md)\n");
+
+        let chapter = |content: &str, sub_items: Value| {
+            serde_json::json!({ "Chapter": {
+                "name": "Intro", "content": content, "number": [1], "sub_items": sub_items,
+                "path": "intro.md", "source_path": "intro.md", "parent_names": []
+            }})
+        };
+        let input = serde_json::json!([
+            {
+                "root": dir.join("guide"),
+                "config": { "preprocessor": { "stklr": { "crate": "..", "api-url": "/api/" } } },
+                "renderer": "html",
+                "mdbook_version": "0.4.21"
+            },
+            {
+                "sections": [
+                    chapter("# Thing\nA Thing returns a bool.\n", serde_json::json!([
+                        chapter("Send a `Frame`.\n", serde_json::json!([]))
+                    ])),
+                    "Separator",
+                    chapter(
+                        "Returns true if it's None, or a Vec<T> of [the bool kind](bool.md).\n\
+                         ```\nlet none: Option<u8> = None;\n```\n",
+                        serde_json::json!([])
+                    )
+                ],
+                "__non_exhaustive": null
+            }
+        ]);
+
+        let out = preprocess(&input.to_string(), &Config::default()).unwrap();
+        let book: Value = serde_json::from_str(&out).unwrap();
+        let intro = &book["sections"][0]["Chapter"];
+        assert_eq!(
+            intro["content"],
+            "# Thing\nA [`Thing`](/api/my_crate/struct.Thing.html) returns a `bool`.\n"
+        );
+        assert_eq!(
+            intro["sub_items"][0]["Chapter"]["content"],
+            "Send a [`Frame`](/api/my_crate/wire/struct.Frame.html).\n"
+        );
+        assert_eq!(book["sections"][1], "Separator");
+        assert_eq!(
+            book["sections"][2]["Chapter"]["content"],
+            "Returns `true` if it's `None`, or a `Vec<T>` of [the bool kind](bool.md).\n\
+             ```\nlet none: Option<u8> = None;\n```\n"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//!
+//! Migration of rust-doc's old html path links, i.e `[Foo](struct.Foo.html)`, to intra-doc links,
+//! which don't break whenever a module moves.
+//!
+use super::{
+    consts::{RUST_HTML_LINK, RUST_HTML_LINK_DEF},
+    symbols::{display_path, Symbol},
+    utils::{Flavour, RawSourceCode},
+};
+
+use std::path::PathBuf;
+
+/// A link written by hand that couldn't be resolved against the symbol table, and so was left
+/// alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    pub source_file: PathBuf,
+    pub line_num: usize,
+    pub dest: String,
+}
+
+/// The flavours rust-doc gives pages of their own.
+pub(crate) const PAGE_FLAVOURS: &[Flavour] = &[
+    Flavour::RUST_STRUCT,
+    Flavour::RUST_ENUM,
+    Flavour::RUST_TRAIT,
+    Flavour::RUST_FN,
+    Flavour::RUST_TY,
+    Flavour::RUST_MACRO,
+    Flavour::RUST_CONST,
+    Flavour::RUST_STATIC,
+    Flavour::RUST_UNION,
+];
+
+/// Rewrites the html path links in the docs of `rsc` in place, returning the lines changed and
+/// the links that couldn't be resolved.
+pub fn migrate_html_links(
+    rsc: &mut RawSourceCode,
+    symbols: &[Symbol],
+) -> (Vec<usize>, Vec<Unresolved>) {
+    let mut changed = Vec::new();
+    let mut unresolved = Vec::new();
+    let module = rsc.module_path.clone();
+
+    for rl in rsc
+        .m
+        .values_mut()
+        .filter(|rl| rl.flavour == Flavour::RUST_DOCS)
+    {
+        let mut missed = Vec::new();
+        let mut resolve = |dest: &str| {
+            let resolved = resolve_html_path(&module, dest, symbols);
+            if resolved.is_none() {
+                missed.push(dest.to_string());
+            }
+            resolved
+        };
+
+        let contents = RUST_HTML_LINK
+            .replace_all(&rl.contents, |caps: &regex::Captures| {
+                match resolve(&caps["dest"]) {
+                    Some(target) => format!("[{}]({})", &caps["text"], target),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned();
+        let contents = RUST_HTML_LINK_DEF
+            .replace(&contents, |caps: &regex::Captures| {
+                match resolve(&caps["dest"]) {
+                    Some(target) => format!("{}{}", &caps["def"], target),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned();
+
+        unresolved.extend(missed.into_iter().map(|dest| Unresolved {
+            source_file: rsc.file.clone(),
+            line_num: rl.line_num,
+            dest,
+        }));
+        if contents != rl.contents {
+            rl.contents = contents;
+            changed.push(rl.line_num);
+        }
+    }
+    changed.sort();
+    unresolved.sort_by_key(|u| u.line_num);
+    (changed, unresolved)
+}
+
+/// Resolves the html path `dest`, relative to the page of `module`, to an intra-doc destination,
+/// i.e `../utils/struct.SourceTree.html#method.new` to `crate::search::utils::SourceTree::new`.
+pub fn resolve_html_path(module: &[String], dest: &str, symbols: &[Symbol]) -> Option<String> {
+    let (page, anchor) = match dest.split_once('#') {
+        Some((page, anchor)) => (page, Some(anchor)),
+        None => (dest, None),
+    };
+    let mut dirs = page.split('/').collect::<Vec<&str>>();
+    let file = dirs.pop()?;
+
+    let mut path = module.to_vec();
+    for dir in dirs {
+        match dir {
+            "" | "." => (),
+            // Out of the crate altogether, i.e another crate's docs.
+            ".." => {
+                path.pop()?;
+            }
+            dir => path.push(dir.to_string()),
+        }
+    }
+
+    let (flavour, name) = match file {
+        "index.html" => match path.pop() {
+            Some(name) => (Flavour::RUST_MOD, name),
+            None => return anchor.is_none().then(|| "crate".to_string()),
+        },
+        file => {
+            let (kind, name) = file.strip_suffix(".html")?.split_once('.')?;
+            let flavour = PAGE_FLAVOURS.iter().find(|f| f.html_kind() == Some(kind))?;
+            (flavour.clone(), name.to_string())
+        }
+    };
+    path.push(name.clone());
+
+    let candidates = symbols
+        .iter()
+        .filter(|sym| !sym.associated && sym.flavour == flavour)
+        .filter(|sym| sym.ident.trim_end_matches('!') == name)
+        .collect::<Vec<&Symbol>>();
+    let sym = match candidates
+        .iter()
+        .find(|sym| sym.path == path || sym.public_paths.contains(&path))
+    {
+        Some(sym) => sym,
+        // The page may simply have moved since, if there's only the one it's that.
+        None if candidates.len() == 1 => candidates[0],
+        None => return None,
+    };
+
+    let mut target = sym.link_target().unwrap_or_else(|| {
+        let mut target = display_path("crate", &sym.path);
+        if flavour == Flavour::RUST_MACRO {
+            target.push('!');
+        }
+        target
+    });
+    if let Some(anchor) = anchor {
+        let (kind, member) = anchor.split_once('.')?;
+        match kind {
+            "method" | "tymethod" | "variant" | "structfield" | "associatedconstant"
+            | "associatedtype" => target.push_str(&format!("::{}", member)),
+            _ => return None,
+        }
+    }
+    Some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use crate::search::utils::SourceTree;
+
+    #[test]
+    fn resolves_html_paths() {
+        let dir = TestDir::new("migrate");
+        dir.write(
+            "src/lib.rs",
+            "pub mod module;\n\
+             /// A [Foo](struct.Foo.html), [bar](module/fn.bar.html) and [gone](fn.gone.html).\n\
+             pub struct Foo;\n",
+        );
+        dir.write(
+            "src/module/mod.rs",
+            "/// Back to [`Foo::new`](../struct.Foo.html#method.new).\n\
+             ///\n\
+             /// [Foo]: ../struct.Foo.html\n\
+             pub fn bar() {}\n",
+        );
+
+        let mut st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let symbols = st.symbols.clone();
+        let mut lib = None;
+        let mut module = None;
+        for sf in st.source_files.iter_mut() {
+            let migrated = migrate_html_links(sf, &symbols);
+            match sf.module_path.is_empty() {
+                true => lib = Some((sf.clone(), migrated)),
+                false => module = Some(sf.clone()),
+            }
+        }
+        let (lib, (changed, unresolved)) = lib.unwrap();
+        let module = module.unwrap();
+
+        assert_eq!(changed, vec![1]);
+        assert_eq!(
+            lib.get(&1).unwrap().contents,
+            "/// A [Foo](crate::Foo), [bar](crate::module::bar) and [gone](fn.gone.html)."
+        );
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].dest, "fn.gone.html");
+        assert_eq!(
+            module.get(&0).unwrap().contents,
+            "/// Back to [`Foo::new`](crate::Foo::new)."
+        );
+        assert_eq!(module.get(&2).unwrap().contents, "/// [Foo]: crate::Foo");
+    }
+}
@@ -1,2 +1,23 @@
+pub mod confidence;
+pub mod config;
 pub mod consts;
+pub mod deps;
+pub mod docs;
+pub mod export;
+pub mod glossary;
+pub mod lsp;
+pub mod markdown;
+pub mod mdbook;
+pub mod migrate;
+pub mod replace;
+pub mod rules;
+pub mod shorthand;
+pub mod std_index;
+pub mod symbols;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod ticks;
+pub mod uses;
 pub mod utils;
+pub mod visibility;
+pub mod workspace;
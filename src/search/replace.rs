@@ -0,0 +1,177 @@
+//!
+//! Regex find-and-replace rules, written in the `[[rules]]` of the config file, i.e linking
+//! `RFC 1234` to the RFC or `#123` to an issue tracker.
+//!
+use super::rules::{Rule, RuleContext, SpanEdit};
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::ops::Range;
+
+/// Which parts of a doc line a [`ReplaceRule`] can change.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// The text, code spans excluded.
+    #[default]
+    Prose,
+    /// All of it, code spans included.
+    Docs,
+}
+
+/// A rule as it's written in the config file:
+///
+/// ```toml
+/// [[rules]]
+/// name = "rfc"
+/// pattern = 'RFC (?P<num>\d+)'
+/// replace = "[RFC $num](https://rust-lang.github.io/rfcs/$num.html)"
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceRule {
+    pub name: String,
+    pub pattern: String,
+    /// What a match becomes, `$1` or `$name` being what the pattern captured.
+    pub replace: String,
+    #[serde(default)]
+    pub scope: Scope,
+}
+
+lazy_static! {
+    // Existing links are left alone whatever the scope, they're likely what a rule made last time.
+    static ref LINK: Regex = Regex::new(r"\[[^\]]*\](?:\([^)]*\))?").unwrap();
+    // Autolinks, i.e `<https://example.com>`.
+    static ref AUTOLINK: Regex = Regex::new(r"<[a-zA-Z][\w+.-]*:[^\s<>]*>").unwrap();
+    // What a reference definition, i.e `[ref]: https://example.com`, points to.
+    static ref LINK_DEF: Regex = Regex::new(r"^\s*(?://[/!])?\s*\[[^\]]+\]:.*$").unwrap();
+    static ref CODE_SPAN: Regex = Regex::new(r"`[^`]*`").unwrap();
+}
+
+/// The existing links in `contents`, autolinks and reference definitions included, and its code
+/// spans too if `code_spans`, which aren't to be touched. Links are the ranges starting with `[`.
+pub fn protected_ranges(contents: &str, code_spans: bool) -> Vec<Range<usize>> {
+    let mut protected = LINK
+        .find_iter(contents)
+        .chain(AUTOLINK.find_iter(contents))
+        .chain(LINK_DEF.find_iter(contents))
+        .map(|m| m.range())
+        .collect::<Vec<Range<usize>>>();
+    if code_spans {
+        protected.extend(CODE_SPAN.find_iter(contents).map(|m| m.range()));
+    }
+    protected
+}
+
+/// A [`ReplaceRule`] ready to [`Rule::apply`].
+#[derive(Debug, Clone)]
+pub struct RegexRule {
+    pub name: String,
+    pub regex: Regex,
+    pub replace: String,
+    pub scope: Scope,
+}
+
+impl RegexRule {
+    pub fn new(rule: &ReplaceRule) -> Result<Self> {
+        Ok(Self {
+            name: rule.name.clone(),
+            regex: Regex::new(&rule.pattern)
+                .with_context(|| format!("Invalid pattern in rule {}", rule.name))?,
+            replace: rule.replace.clone(),
+            scope: rule.scope,
+        })
+    }
+
+    /// The replacements for `contents`, matches overlapping a link (or code span when the scope
+    /// is [`Scope::Prose`]) are skipped.
+    pub fn replacements(&self, contents: &str) -> Vec<(Range<usize>, String)> {
+        let protected = protected_ranges(contents, self.scope == Scope::Prose);
+
+        self.regex
+            .captures_iter(contents)
+            .filter_map(|caps| {
+                let m = caps.get(0)?;
+                if m.as_str().is_empty()
+                    || protected
+                        .iter()
+                        .any(|p| p.start < m.end() && m.start() < p.end)
+                {
+                    return None;
+                }
+                let mut replacement = String::new();
+                caps.expand(&self.replace, &mut replacement);
+                Some((m.range(), replacement))
+            })
+            .collect()
+    }
+}
+
+impl Rule for RegexRule {
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit> {
+        ctx.prose()
+            .flat_map(|rl| {
+                self.replacements(&rl.contents)
+                    .into_iter()
+                    .map(|(range, replacement)| SpanEdit {
+                        line_num: rl.line_num,
+                        message: format!("replaced {}", &rl.contents[range.clone()]),
+                        range,
+                        replacement,
+                        rule: self.name.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::config::ConfigFile;
+
+    #[test]
+    fn replaces_prose_only() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [[rules]]
+            name = "rfc"
+            pattern = 'RFC (?P<num>\d+)'
+            replace = "[RFC $num](https://rust-lang.github.io/rfcs/$num.html)"
+
+            [[rules]]
+            name = "issue"
+            pattern = '#(\d+)'
+            replace = "[#$1](https://github.com/alphastrata/stklr/issues/$1)"
+            scope = "docs"
+            "#,
+        )
+        .unwrap();
+        let rules = file.replace_rules().unwrap();
+
+        let line = "/// See RFC 1234, not `RFC 42` or [RFC 7](x), and #3 in `#4`.";
+        let replaced = |rule: &RegexRule| {
+            let mut out = line.to_string();
+            for (range, text) in rule.replacements(line).into_iter().rev() {
+                out.replace_range(range, &text);
+            }
+            out
+        };
+        assert_eq!(
+            replaced(&rules[0]),
+            "/// See [RFC 1234](https://rust-lang.github.io/rfcs/1234.html), not `RFC 42` or \
+             [RFC 7](x), and #3 in `#4`."
+        );
+        assert_eq!(
+            replaced(&rules[1]),
+            "/// See RFC 1234, not `RFC 42` or [RFC 7](x), and \
+             [#3](https://github.com/alphastrata/stklr/issues/3) in \
+             `[#4](https://github.com/alphastrata/stklr/issues/4)`."
+        );
+    }
+}
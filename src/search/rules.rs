@@ -0,0 +1,299 @@
+//!
+//! Doc transformations as [`Rule`]s. The built-in linking and backticking are rules like any other,
+//! so a crate can add its own from a small wrapper binary, see [`crate::cmd::jobs::run_with`].
+//!
+use super::{
+    config::{Config, LinkPolicy},
+    docs::DocBlock,
+    symbols::Symbol,
+    ticks::tick_code_tokens,
+    utils::{RawLine, RawSourceCode},
+};
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+};
+
+/// A change a [`Rule`] wants made to one doc line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpanEdit {
+    pub line_num: usize,
+    /// The bytes of the line's contents replaced.
+    pub range: Range<usize>,
+    pub replacement: String,
+    /// The [`Rule::id`] of the rule that made it.
+    pub rule: String,
+    pub message: String,
+}
+
+impl SpanEdit {
+    /// The smallest edit turning line `line_num` from `old` into `new`, if they differ at all.
+    pub fn between(
+        line_num: usize,
+        old: &str,
+        new: &str,
+        rule: &str,
+        message: String,
+    ) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| old.len().min(new.len()));
+        let suffix = old[prefix..]
+            .chars()
+            .rev()
+            .zip(new[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>();
+
+        Some(Self {
+            line_num,
+            range: prefix..old.len() - suffix,
+            replacement: new[prefix..new.len() - suffix].to_string(),
+            rule: rule.to_string(),
+            message,
+        })
+    }
+
+    /// `contents` with this edit made, `None` when the range doesn't fit them.
+    pub fn apply(&self, contents: &str) -> Option<String> {
+        let Range { start, end } = self.range;
+        if start > end || !contents.is_char_boundary(start) || !contents.is_char_boundary(end) {
+            return None;
+        }
+        Some(format!(
+            "{}{}{}",
+            &contents[..start],
+            self.replacement,
+            &contents[end..]
+        ))
+    }
+}
+
+/// What a [`Rule`] gets to see of the [`DocBlock`] it's applied to.
+pub struct RuleContext<'a> {
+    pub block: &'a DocBlock,
+    /// The block's lines as the rules before this one left them.
+    pub lines: &'a BTreeMap<usize, RawLine>,
+    /// The file the block is from, its link targets, private idents and so on.
+    pub source: &'a RawSourceCode,
+    /// The idents that can be linked.
+    pub idents: &'a [String],
+    pub symbols: &'a [Symbol],
+    pub cfg: &'a Config,
+}
+
+impl RuleContext<'_> {
+    /// The block's lines of prose, fenced code left out, which is all the built-in rules touch.
+    pub fn prose(&self) -> impl Iterator<Item = &RawLine> {
+        self.lines
+            .values()
+            .filter(|rl| !self.block.is_fenced(rl.line_num))
+    }
+}
+
+/// A transformation of doc comments, applied to one [`DocBlock`] at a time in the order the rules
+/// are given.
+pub trait Rule {
+    /// A short name for the rule, shown alongside its edits.
+    fn id(&self) -> &str;
+
+    /// The edits to make to the block, those overlapping an edit already made are dropped.
+    fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit>;
+}
+
+/// The rules run unless told otherwise: backticking code-ish tokens, then linking mentions.
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    vec![Box::new(CodeTokens), Box::new(LinkMentions)]
+}
+
+/// Backticks primitives, literals and the like, see [`super::ticks`].
+pub struct CodeTokens;
+
+impl Rule for CodeTokens {
+    fn id(&self) -> &str {
+        "code-tokens"
+    }
+
+    fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit> {
+        ctx.prose()
+            .filter_map(|rl| {
+                let ticked = tick_code_tokens(&rl.contents, ctx.cfg.link_primitives);
+                SpanEdit::between(
+                    rl.line_num,
+                    &rl.contents,
+                    &ticked,
+                    self.id(),
+                    "backticked code".into(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Links, or backticks, mentions of items, as plain words and/or code spans as
+/// [`Config::mentions`] says.
+pub struct LinkMentions;
+
+impl Rule for LinkMentions {
+    fn id(&self) -> &str {
+        "link-mentions"
+    }
+
+    fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit> {
+        let (cfg, rsc) = (ctx.cfg, ctx.source);
+        let nothing_private = HashSet::new();
+        // Public docs linking to private items is what rust-doc's `private_intra_doc_links`
+        // lint warns about, private docs can link to whatever they like.
+        let private = match ctx.block.public && !cfg.document_private_items {
+            true => &rsc.private_idents,
+            false => &nothing_private,
+        };
+        // What's been linked so far, see [`LinkPolicy`].
+        let mut seen = HashSet::new();
+        let mut edits = Vec::new();
+
+        for raw_line in ctx.prose() {
+            if raw_line.is_blank_doc() && cfg.link_policy == LinkPolicy::FirstPerParagraph {
+                seen.clear();
+            }
+            if !raw_line.should_be_modified(ctx.idents) {
+                continue;
+            }
+            if cfg.mentions.plain() {
+                edits.extend(raw_line.process_changes(
+                    ctx.idents,
+                    &rsc.link_targets,
+                    ctx.symbols,
+                    ctx.block,
+                    cfg,
+                    &mut seen,
+                    private,
+                    &rsc.word_usage,
+                ));
+            }
+            if cfg.mentions.backticked() {
+                edits.extend(raw_line.link_code_spans(
+                    ctx.idents,
+                    &rsc.link_targets,
+                    ctx.symbols,
+                    ctx.block,
+                    cfg,
+                    &mut seen,
+                    private,
+                ));
+            }
+        }
+        for edit in edits.iter_mut() {
+            edit.rule = self.id().into();
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use crate::search::utils::SourceTree;
+
+    /// Emboldens a word everywhere it appears, as a crate might its product's name.
+    struct Product;
+
+    impl Rule for Product {
+        fn id(&self) -> &str {
+            "product"
+        }
+
+        fn apply(&self, ctx: &RuleContext) -> Vec<SpanEdit> {
+            ctx.lines
+                .values()
+                .flat_map(|rl| {
+                    rl.contents
+                        .match_indices("Widget")
+                        .map(|(i, w)| SpanEdit {
+                            line_num: rl.line_num,
+                            range: i..i + w.len(),
+                            replacement: "**Widget**".into(),
+                            rule: self.id().into(),
+                            message: "product name".into(),
+                        })
+                        .collect::<Vec<SpanEdit>>()
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn custom_rules_run_after_builtins() {
+        let dir = TestDir::new("rules");
+        dir.write(
+            "src/lib.rs",
+            "/// A Widget made by Thing, returns a bool.\npub struct Thing;\n",
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let mut rules = builtin_rules();
+        rules.push(Box::new(Product));
+        let adjusted =
+            rsc.make_adjustments(&rsc.named_idents, &st.symbols, &rules, &Default::default());
+
+        assert_eq!(
+            adjusted[0].contents,
+            "/// A **Widget** made by `Thing`, returns a `bool`."
+        );
+        let rules = adjusted[0]
+            .edits
+            .iter()
+            .map(|e| e.rule.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(rules, vec!["code-tokens", "link-mentions", "product"]);
+    }
+
+    #[test]
+    fn skips_doctests() {
+        let dir = TestDir::new("rules_fences");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "/// Makes a Thing from a bool.",
+                "///",
+                "/// ```",
+                "/// let thing: Thing = make(true) as Thing;",
+                "/// ```",
+                "pub fn make(fast: bool) -> Thing { Thing }",
+                "pub struct Thing;",
+            ]
+            .join("\n"),
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let adjusted = rsc.make_adjustments(
+            &rsc.named_idents,
+            &st.symbols,
+            &builtin_rules(),
+            &Default::default(),
+        );
+
+        assert_eq!(adjusted.len(), 1);
+        assert_eq!(adjusted[0].contents, "/// Makes a [`Thing`] from a `bool`.");
+    }
+
+    #[test]
+    fn smallest_edit() {
+        let edit =
+            SpanEdit::between(3, "/// a Foo b", "/// a [`Foo`] b", "x", String::new()).unwrap();
+        assert_eq!(edit.range, 6..9);
+        assert_eq!(edit.replacement, "[`Foo`]");
+        assert_eq!(edit.apply("/// a Foo b").unwrap(), "/// a [`Foo`] b");
+    }
+}
@@ -0,0 +1,238 @@
+//!
+//! Expansion of the wiki-style shorthand writers use to mark links by hand, i.e `[[Foo]]`,
+//! `[[Foo::bar]]` or `[[Foo|the foo type]]`.
+//!
+use super::{
+    config::{Config, PrivateLinks},
+    consts::RUST_WIKI_LINK,
+    migrate::Unresolved,
+    symbols::{display_path, resolves_member, LinkTarget, Symbol},
+    utils::{split_item_path, Flavour, RawSourceCode},
+};
+
+/// Expands the shorthand in the docs of `rsc` in place, returning the lines changed and the
+/// shorthand naming something that doesn't exist, which is left as it is. Private items aren't
+/// linked from public docs, they're written out as [`Config::private_links`] says.
+pub fn expand_shorthand(
+    rsc: &mut RawSourceCode,
+    symbols: &[Symbol],
+    cfg: &Config,
+) -> (Vec<usize>, Vec<Unresolved>) {
+    let mut changed = Vec::new();
+    let mut unresolved = Vec::new();
+    let mut lines = rsc
+        .m
+        .iter()
+        .filter(|(_, rl)| rl.flavour == Flavour::RUST_DOCS && rl.contents.contains("[["))
+        .map(|(n, rl)| (*n, rl.contents.clone()))
+        .collect::<Vec<(usize, String)>>();
+    lines.sort();
+
+    for (n, contents) in lines {
+        // Public docs linking to private items is what rust-doc's `private_intra_doc_links`
+        // lint warns about, see [`super::rules::LinkMentions`].
+        let public = rsc
+            .doc_blocks
+            .iter()
+            .find(|block| block.lines.contains(&n))
+            .map(|block| block.public)
+            .unwrap_or(true);
+        let link_private = !public || cfg.document_private_items;
+        let expanded = RUST_WIKI_LINK
+            .replace_all(&contents, |caps: &regex::Captures| {
+                // Inside a code span, i.e talking about the shorthand rather than using it.
+                let start = caps.get(0).unwrap().start();
+                if contents[..start].matches('`').count() % 2 == 1 {
+                    return caps[0].to_string();
+                }
+                let text = caps.name("text").map(|t| t.as_str().trim());
+                match expand(rsc, symbols, &caps["path"], text) {
+                    Some((_, true)) if !link_private => {
+                        let written = text.unwrap_or(&caps["path"]);
+                        match (cfg.private_links, text) {
+                            (PrivateLinks::Backtick, None) => format!("`{}`", written),
+                            _ => written.to_string(),
+                        }
+                    }
+                    Some((link, _)) => link,
+                    None => {
+                        unresolved.push(Unresolved {
+                            source_file: rsc.file.clone(),
+                            line_num: n,
+                            dest: caps[0].to_string(),
+                        });
+                        caps[0].to_string()
+                    }
+                }
+            })
+            .into_owned();
+
+        if expanded != contents {
+            rsc.m.get_mut(&n).unwrap().contents = expanded;
+            changed.push(n);
+        }
+    }
+    (changed, unresolved)
+}
+
+/// The link `[[path|text]]` expands to, items in scope in `rsc` are linked as they are and anything
+/// else through where it's declared. Along with whether the item is private.
+fn expand(
+    rsc: &RawSourceCode,
+    symbols: &[Symbol],
+    path: &str,
+    text: Option<&str>,
+) -> Option<(String, bool)> {
+    let (head, rest) = split_item_path(path)?;
+    if !resolves_member(symbols, head, &rest) {
+        return None;
+    }
+
+    let (target, private) = match rsc.link_targets.get(head) {
+        Some(target) => (Some(target.extend(&rest)?), false),
+        None if rsc.named_idents.iter().any(|i| i == head) => {
+            (None, rsc.private_idents.contains(head))
+        }
+        None => {
+            let (dest, public) = declared(symbols, head)?;
+            (
+                Some(LinkTarget::Explicit(format!("{}{}", dest, rest))),
+                !public,
+            )
+        }
+    };
+
+    let link = match (target, text) {
+        (None, None) => format!("[`{}`]", path),
+        (None, Some(text)) => format!("[{}]({})", text, path),
+        (Some(target), None) => target.link(path),
+        (Some(target), Some(text)) => format!("[{}]({})", text, target.dest()),
+    };
+    Some((link, private))
+}
+
+/// The path of the one item named `ident`, its canonical one when it's public, and whether it is.
+fn declared(symbols: &[Symbol], ident: &str) -> Option<(String, bool)> {
+    let mut found = symbols
+        .iter()
+        .filter(|sym| !sym.associated && sym.ident == ident)
+        .map(|sym| match sym.link_target() {
+            Some(target) => (target, true),
+            None => {
+                let mut target = display_path("crate", &sym.path);
+                if sym.flavour == Flavour::RUST_MACRO {
+                    target.push('!');
+                }
+                (target, false)
+            }
+        })
+        .collect::<Vec<(String, bool)>>();
+    found.sort();
+    found.dedup();
+    match found.len() {
+        1 => found.pop(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::test_utils::TestDir;
+    use crate::search::utils::SourceTree;
+
+    #[test]
+    fn expands_shorthand() {
+        let dir = TestDir::new("shorthand");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "pub mod inner;",
+                "/// See [[Thing]], [[Thing::build]], [[Thing|the thing]] and [[Missing]], not `[[Thing]]`.",
+                "/// Nor [[Thing::finish]], that's an Other's.",
+                "pub fn helper() {}",
+            ]
+            .join("\n"),
+        );
+        dir.write(
+            "src/inner.rs",
+            "pub struct Thing;\nimpl Thing {\n    pub fn build() {}\n}\n\
+             pub struct Other;\nimpl Other {\n    pub fn finish() {}\n}\n",
+        );
+
+        let mut st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let symbols = st.symbols.clone();
+        let lib = st
+            .source_files
+            .iter_mut()
+            .find(|sf| sf.module_path.is_empty())
+            .unwrap();
+        let (changed, unresolved) = expand_shorthand(lib, &symbols, &Config::default());
+
+        assert_eq!(changed, vec![1]);
+        assert_eq!(
+            lib.get(&1).unwrap().contents,
+            "/// See [`Thing`](crate::inner::Thing), [`Thing::build`](crate::inner::Thing::build), \
+             [the thing](crate::inner::Thing) and [[Missing]], not `[[Thing]]`."
+        );
+        assert_eq!(
+            lib.get(&2).unwrap().contents,
+            "/// Nor [[Thing::finish]], that's an Other's."
+        );
+        let unresolved = unresolved
+            .iter()
+            .map(|u| u.dest.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(unresolved, vec!["[[Missing]]", "[[Thing::finish]]"]);
+    }
+
+    #[test]
+    fn keeps_private_items_out_of_public_docs() {
+        let dir = TestDir::new("shorthand_private");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "mod inner;",
+                "/// See [[Thing]] or [[Thing|the thing]].",
+                "pub fn helper() {}",
+                "/// See [[Thing]].",
+                "fn private_helper() {}",
+            ]
+            .join("\n"),
+        );
+        dir.write("src/inner.rs", "pub struct Thing;\n");
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let expanded = |cfg: &Config| {
+            let mut lib = st
+                .source_files
+                .iter()
+                .find(|sf| sf.module_path.is_empty())
+                .unwrap()
+                .clone();
+            expand_shorthand(&mut lib, &st.symbols, cfg);
+            [1, 3].map(|n| lib.get(&n).unwrap().contents.clone())
+        };
+
+        assert_eq!(
+            expanded(&Config::default()),
+            [
+                "/// See Thing or the thing.",
+                "/// See [`Thing`](crate::inner::Thing)."
+            ]
+        );
+        let cfg = Config {
+            private_links: PrivateLinks::Backtick,
+            ..Default::default()
+        };
+        assert_eq!(expanded(&cfg)[0], "/// See `Thing` or the thing.");
+        let cfg = Config {
+            document_private_items: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            expanded(&cfg)[0],
+            "/// See [`Thing`](crate::inner::Thing) or [the thing](crate::inner::Thing)."
+        );
+    }
+}
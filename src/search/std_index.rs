@@ -0,0 +1,251 @@
+//!
+//! A bundled, offline index of commonly mentioned `std`/`core`/`alloc` items, so they can be
+//! linked without needing a toolchain's docs around.
+//!
+use super::symbols::LinkTarget;
+
+use std::collections::HashMap;
+
+/// `(name, std path, no_std path)`, items that only exist in `std` have no no_std path. Items whose
+/// names are everyday words (`Default`, `Error`, `Path`, `File`, `Duration` etc) are deliberately
+/// left out, but for the few that are nearly always the type in docs: `Option`, `Result`, `String`
+/// and `Box`.
+pub const STD_INDEX: &[(&str, &str, Option<&str>)] = &[
+    // Collections
+    ("Vec", "std::vec::Vec", Some("alloc::vec::Vec")),
+    (
+        "VecDeque",
+        "std::collections::VecDeque",
+        Some("alloc::collections::VecDeque"),
+    ),
+    (
+        "LinkedList",
+        "std::collections::LinkedList",
+        Some("alloc::collections::LinkedList"),
+    ),
+    (
+        "BinaryHeap",
+        "std::collections::BinaryHeap",
+        Some("alloc::collections::BinaryHeap"),
+    ),
+    (
+        "BTreeMap",
+        "std::collections::BTreeMap",
+        Some("alloc::collections::BTreeMap"),
+    ),
+    (
+        "BTreeSet",
+        "std::collections::BTreeSet",
+        Some("alloc::collections::BTreeSet"),
+    ),
+    ("HashMap", "std::collections::HashMap", None),
+    ("HashSet", "std::collections::HashSet", None),
+    // Strings
+    (
+        "String",
+        "std::string::String",
+        Some("alloc::string::String"),
+    ),
+    (
+        "ToString",
+        "std::string::ToString",
+        Some("alloc::string::ToString"),
+    ),
+    ("OsString", "std::ffi::OsString", None),
+    ("OsStr", "std::ffi::OsStr", None),
+    ("CString", "std::ffi::CString", Some("alloc::ffi::CString")),
+    ("CStr", "std::ffi::CStr", Some("core::ffi::CStr")),
+    ("Cow", "std::borrow::Cow", Some("alloc::borrow::Cow")),
+    // Options and results
+    (
+        "Option",
+        "std::option::Option",
+        Some("core::option::Option"),
+    ),
+    (
+        "Result",
+        "std::result::Result",
+        Some("core::result::Result"),
+    ),
+    // Smart pointers and cells
+    ("Box", "std::boxed::Box", Some("alloc::boxed::Box")),
+    ("Rc", "std::rc::Rc", Some("alloc::rc::Rc")),
+    ("Arc", "std::sync::Arc", Some("alloc::sync::Arc")),
+    ("RefCell", "std::cell::RefCell", Some("core::cell::RefCell")),
+    (
+        "OnceCell",
+        "std::cell::OnceCell",
+        Some("core::cell::OnceCell"),
+    ),
+    ("NonNull", "std::ptr::NonNull", Some("core::ptr::NonNull")),
+    ("Pin", "std::pin::Pin", Some("core::pin::Pin")),
+    (
+        "PhantomData",
+        "std::marker::PhantomData",
+        Some("core::marker::PhantomData"),
+    ),
+    (
+        "NonZeroUsize",
+        "std::num::NonZeroUsize",
+        Some("core::num::NonZeroUsize"),
+    ),
+    (
+        "NonZeroU32",
+        "std::num::NonZeroU32",
+        Some("core::num::NonZeroU32"),
+    ),
+    (
+        "NonZeroU64",
+        "std::num::NonZeroU64",
+        Some("core::num::NonZeroU64"),
+    ),
+    // Traits
+    (
+        "Iterator",
+        "std::iter::Iterator",
+        Some("core::iter::Iterator"),
+    ),
+    (
+        "IntoIterator",
+        "std::iter::IntoIterator",
+        Some("core::iter::IntoIterator"),
+    ),
+    (
+        "DoubleEndedIterator",
+        "std::iter::DoubleEndedIterator",
+        Some("core::iter::DoubleEndedIterator"),
+    ),
+    (
+        "ExactSizeIterator",
+        "std::iter::ExactSizeIterator",
+        Some("core::iter::ExactSizeIterator"),
+    ),
+    (
+        "FromIterator",
+        "std::iter::FromIterator",
+        Some("core::iter::FromIterator"),
+    ),
+    (
+        "TryFrom",
+        "std::convert::TryFrom",
+        Some("core::convert::TryFrom"),
+    ),
+    (
+        "TryInto",
+        "std::convert::TryInto",
+        Some("core::convert::TryInto"),
+    ),
+    ("AsRef", "std::convert::AsRef", Some("core::convert::AsRef")),
+    ("AsMut", "std::convert::AsMut", Some("core::convert::AsMut")),
+    ("FromStr", "std::str::FromStr", Some("core::str::FromStr")),
+    (
+        "PartialEq",
+        "std::cmp::PartialEq",
+        Some("core::cmp::PartialEq"),
+    ),
+    (
+        "PartialOrd",
+        "std::cmp::PartialOrd",
+        Some("core::cmp::PartialOrd"),
+    ),
+    ("Hasher", "std::hash::Hasher", Some("core::hash::Hasher")),
+    ("Deref", "std::ops::Deref", Some("core::ops::Deref")),
+    (
+        "DerefMut",
+        "std::ops::DerefMut",
+        Some("core::ops::DerefMut"),
+    ),
+    ("FnMut", "std::ops::FnMut", Some("core::ops::FnMut")),
+    ("FnOnce", "std::ops::FnOnce", Some("core::ops::FnOnce")),
+    (
+        "RangeInclusive",
+        "std::ops::RangeInclusive",
+        Some("core::ops::RangeInclusive"),
+    ),
+    (
+        "Formatter",
+        "std::fmt::Formatter",
+        Some("core::fmt::Formatter"),
+    ),
+    ("TypeId", "std::any::TypeId", Some("core::any::TypeId")),
+    ("Waker", "std::task::Waker", Some("core::task::Waker")),
+    // Time
+    ("SystemTime", "std::time::SystemTime", None),
+    // Sync
+    ("Mutex", "std::sync::Mutex", None),
+    ("MutexGuard", "std::sync::MutexGuard", None),
+    ("RwLock", "std::sync::RwLock", None),
+    ("Condvar", "std::sync::Condvar", None),
+    ("OnceLock", "std::sync::OnceLock", None),
+    (
+        "AtomicBool",
+        "std::sync::atomic::AtomicBool",
+        Some("core::sync::atomic::AtomicBool"),
+    ),
+    (
+        "AtomicUsize",
+        "std::sync::atomic::AtomicUsize",
+        Some("core::sync::atomic::AtomicUsize"),
+    ),
+    (
+        "AtomicU64",
+        "std::sync::atomic::AtomicU64",
+        Some("core::sync::atomic::AtomicU64"),
+    ),
+    ("JoinHandle", "std::thread::JoinHandle", None),
+    // IO, filesystem, processes and networking
+    ("PathBuf", "std::path::PathBuf", None),
+    ("OpenOptions", "std::fs::OpenOptions", None),
+    ("DirEntry", "std::fs::DirEntry", None),
+    ("BufRead", "std::io::BufRead", None),
+    ("BufReader", "std::io::BufReader", None),
+    ("BufWriter", "std::io::BufWriter", None),
+    ("Stdin", "std::io::Stdin", None),
+    ("Stdout", "std::io::Stdout", None),
+    ("Stderr", "std::io::Stderr", None),
+    ("ExitCode", "std::process::ExitCode", None),
+    ("TcpStream", "std::net::TcpStream", None),
+    ("TcpListener", "std::net::TcpListener", None),
+    ("UdpSocket", "std::net::UdpSocket", None),
+    ("IpAddr", "std::net::IpAddr", Some("core::net::IpAddr")),
+    (
+        "SocketAddr",
+        "std::net::SocketAddr",
+        Some("core::net::SocketAddr"),
+    ),
+];
+
+/// The std items to link, keyed by name. When `no_std` they're linked through `core`/`alloc`, and
+/// those only found in `std` aren't linked at all.
+pub fn std_targets(no_std: bool) -> HashMap<String, LinkTarget> {
+    STD_INDEX
+        .iter()
+        .filter_map(|(name, std_path, no_std_path)| {
+            let path = match no_std {
+                true => (*no_std_path)?,
+                false => std_path,
+            };
+            Some((name.to_string(), LinkTarget::Explicit(path.to_string())))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_std_paths() {
+        let std = std_targets(false);
+        let no_std = std_targets(true);
+        assert_eq!(
+            std.get("HashMap"),
+            Some(&LinkTarget::Explicit("std::collections::HashMap".into()))
+        );
+        assert_eq!(no_std.get("HashMap"), None);
+        assert_eq!(
+            no_std.get("Vec"),
+            Some(&LinkTarget::Explicit("alloc::vec::Vec".into()))
+        );
+    }
+}
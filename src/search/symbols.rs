@@ -0,0 +1,129 @@
+//!
+//! The items of a [`SourceTree`](super::utils::SourceTree) and the public paths they can be
+//! reached by.
+//!
+use super::utils::Flavour;
+
+use std::path::PathBuf;
+
+/// An item declared somewhere in the tree.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The (linkable) ident, i.e `my_macro!`.
+    pub ident: String,
+    pub flavour: Flavour,
+    /// Where it's declared, i.e `["search", "utils", "SourceTree"]`.
+    pub path: Vec<String>,
+    /// Every path it can be reached by from outside the crate, where it's declared and wherever
+    /// it's `pub use`d, `pub use` chains followed.
+    pub public_paths: Vec<Vec<String>>,
+    /// Declared in an `impl` or `trait`, so its paths are those of the module it's in, not its own.
+    pub associated: bool,
+    /// The type, trait or enum an associated item is declared in, see [`resolves_member`].
+    pub owner: Option<String>,
+    pub source_file: PathBuf,
+    pub line_num: usize,
+}
+
+impl Symbol {
+    pub fn is_public(&self) -> bool {
+        !self.public_paths.is_empty()
+    }
+
+    /// The shortest of the [`Symbol::public_paths`], what rust-doc would call its canonical path.
+    pub fn canonical(&self) -> Option<&Vec<String>> {
+        self.public_paths
+            .iter()
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    /// The intra-doc link destination for this symbol from anywhere in its crate, its
+    /// [`Symbol::canonical`] path.
+    pub fn link_target(&self) -> Option<String> {
+        self.path_from("crate")
+    }
+
+    /// The [`Symbol::canonical`] path, starting from `root`, i.e `crate` or the crate's name when
+    /// linking from another crate.
+    pub fn path_from(&self, root: &str) -> Option<String> {
+        self.canonical().map(|path| {
+            let mut target = display_path(root, path);
+            if self.flavour == Flavour::RUST_MACRO {
+                target.push('!');
+            }
+            target
+        })
+    }
+}
+
+/// Where a link to an ident should point, when it isn't simply in scope as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// Linked as ``[`Foo`](path)``, i.e through an alias or a canonical path.
+    Explicit(String),
+    /// Linked as ``[`path`]``, the full path being shown, i.e for items from other crates.
+    Qualified(String),
+}
+
+impl LinkTarget {
+    /// The markdown for a link to `ident`.
+    pub fn link(&self, ident: &str) -> String {
+        match self {
+            LinkTarget::Explicit(target) => format!("[`{}`]({})", ident, target),
+            LinkTarget::Qualified(path) => format!("[`{}`]", path),
+        }
+    }
+
+    /// Where the link points, be it a path or a URL.
+    pub fn dest(&self) -> &str {
+        match self {
+            LinkTarget::Explicit(target) => target,
+            LinkTarget::Qualified(path) => path,
+        }
+    }
+
+    /// This target with `suffix` appended, i.e `::method()` to link `Foo::method()`, URLs can't be
+    /// extended.
+    pub fn extend(&self, suffix: &str) -> Option<LinkTarget> {
+        match self {
+            LinkTarget::Explicit(url) if url.contains("://") => {
+                suffix.is_empty().then(|| self.clone())
+            }
+            LinkTarget::Explicit(target) => {
+                Some(LinkTarget::Explicit(format!("{}{}", target, suffix)))
+            }
+            LinkTarget::Qualified(path) => {
+                Some(LinkTarget::Qualified(format!("{}{}", path, suffix)))
+            }
+        }
+    }
+}
+
+/// Does `suffix`, the rest of a mention of `head` i.e `::new()`, name something declared on it?
+/// Only the items of the tree are known, members of anything else are taken on trust.
+pub fn resolves_member(symbols: &[Symbol], head: &str, suffix: &str) -> bool {
+    let Some(members) = suffix.strip_prefix("::") else {
+        return true;
+    };
+    let member = members
+        .split("::")
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches("()");
+    let head = head.trim_end_matches('!');
+    let known = symbols
+        .iter()
+        .any(|sym| !sym.associated && sym.ident == head);
+    !known
+        || symbols
+            .iter()
+            .any(|sym| sym.owner.as_deref() == Some(head) && sym.ident == member)
+}
+
+/// Formats a path from `root`, i.e `crate::search::utils::SourceTree`.
+pub fn display_path(root: &str, path: &[String]) -> String {
+    std::iter::once(root)
+        .chain(path.iter().map(|s| s.as_str()))
+        .collect::<Vec<&str>>()
+        .join("::")
+}
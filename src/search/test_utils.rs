@@ -0,0 +1,55 @@
+//!
+//! Fixtures for tests: each test writes the files it needs into a directory of its own, which is
+//! removed once the test is done, so neither leftovers from earlier runs nor other tests running
+//! at the same time can change what it sees.
+//!
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// A fresh, empty directory under the system's temp dir.
+pub(crate) struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "stklr_{}_{}_{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        // Canonical, as paths found by walking the tree are compared against it.
+        let path = path.canonicalize().unwrap();
+        Self { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+
+    /// Writes `contents` to `path`, relative to the directory, making any directories it's in.
+    pub(crate) fn write<P: AsRef<Path>>(&self, path: P, contents: &str) -> PathBuf {
+        let path = self.path.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.path);
+    }
+}
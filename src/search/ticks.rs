@@ -0,0 +1,263 @@
+//!
+//! Backticking of code-ish tokens in prose: primitives, literals, keywords and the like, i.e
+//! "returns a bool" becomes "returns a `bool`".
+//!
+use super::consts::{
+    RUST_BOOL_LITERAL, RUST_GENERIC, RUST_KEYWORD, RUST_LITERAL, RUST_PRIMITIVE, RUST_VARIANT,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// What a [`TickRule`] matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// i.e `u8`, `&str` or `()`
+    Primitive,
+    /// i.e `0u8`, `1.5f32` or `true`
+    Literal,
+    /// i.e `None`, `Some(..)` or `Err(e)`
+    Variant,
+    /// i.e `Self`, `dyn` or `.await`
+    Keyword,
+    /// i.e `Vec<T>` or `Option<&str>`
+    Generic,
+}
+
+/// Where a [`TickRule`]'s matches have to be to get ticked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Anywhere,
+    /// Where a value goes, `true` or `None` are plain English often enough, see
+    /// [`in_code_context`].
+    Value,
+    /// Next to code, as are `fn`, `self` or `str` on their own, see [`near_code`].
+    Code,
+}
+
+/// A kind of code-ish token.
+pub struct TickRule {
+    pub kind: TokenKind,
+    pub pattern: &'static Regex,
+    pub context: Context,
+}
+
+lazy_static! {
+    static ref NONE: Regex = Regex::new(r"^None$").unwrap();
+    static ref BARE_PRIMITIVE: Regex = Regex::new(r"^(?:char|str)$").unwrap();
+    static ref BARE_KEYWORD: Regex = Regex::new(r"^(?:Self|self|dyn|fn|async)$").unwrap();
+    /// The first rule whose pattern matches a token decides, so the bare words come first.
+    pub static ref TICK_RULES: Vec<TickRule> = vec![
+        TickRule {
+            kind: TokenKind::Primitive,
+            pattern: &BARE_PRIMITIVE,
+            context: Context::Code
+        },
+        TickRule {
+            kind: TokenKind::Keyword,
+            pattern: &BARE_KEYWORD,
+            context: Context::Code
+        },
+        TickRule {
+            kind: TokenKind::Primitive,
+            pattern: &RUST_PRIMITIVE,
+            context: Context::Anywhere
+        },
+        TickRule {
+            kind: TokenKind::Literal,
+            pattern: &RUST_LITERAL,
+            context: Context::Anywhere
+        },
+        TickRule {
+            kind: TokenKind::Literal,
+            pattern: &RUST_BOOL_LITERAL,
+            context: Context::Value
+        },
+        TickRule {
+            kind: TokenKind::Variant,
+            pattern: &RUST_VARIANT,
+            context: Context::Anywhere
+        },
+        TickRule {
+            kind: TokenKind::Variant,
+            pattern: &NONE,
+            context: Context::Value
+        },
+        TickRule {
+            kind: TokenKind::Keyword,
+            pattern: &RUST_KEYWORD,
+            context: Context::Anywhere
+        },
+        TickRule {
+            kind: TokenKind::Generic,
+            pattern: &RUST_GENERIC,
+            context: Context::Anywhere
+        },
+    ];
+}
+
+/// Words that come before a value, i.e "returns true" or "is None".
+const BEFORE_VALUE: &[&str] = &[
+    "is",
+    "be",
+    "are",
+    "was",
+    "returns",
+    "return",
+    "yields",
+    "evaluates",
+    "gives",
+    "to",
+    "or",
+    "either",
+    "otherwise",
+];
+
+/// Words that come after a value, i.e "true if" or "None when".
+const AFTER_VALUE: &[&str] = &["if", "when", "otherwise", "or", "and", "unless"];
+
+/// Would `before` or `after` make an ambiguous word like `true` code?
+pub fn in_code_context(before: Option<&str>, after: Option<&str>) -> bool {
+    let clean = |w: &str| {
+        w.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    };
+    before.map(|b| BEFORE_VALUE.contains(&clean(b).as_str())) == Some(true)
+        || after.map(|a| AFTER_VALUE.contains(&clean(a).as_str())) == Some(true)
+}
+
+/// Is `before` or `after` code-ish, with a `::`, `()`, `<>`, `&` or `->` in it, making a word
+/// like `fn` next to it code too?
+pub fn near_code(before: Option<&str>, after: Option<&str>) -> bool {
+    [before, after]
+        .iter()
+        .flatten()
+        .any(|w| ["::", "()", "<", ">", "&"].iter().any(|c| w.contains(c)))
+}
+
+/// Backticks the code-ish tokens of the doc `line`, primitives are linked to their pages in `std`
+/// instead when `link_primitives` is set. Everything else about the line is left as it was.
+pub fn tick_code_tokens(line: &str, link_primitives: bool) -> String {
+    let tokens = TOKEN.find_iter(line).collect::<Vec<regex::Match>>();
+    let mut out = String::new();
+    let mut last = 0;
+
+    for (n, token) in tokens.iter().enumerate() {
+        // Already code, a link, or inside a code span.
+        if token.as_str().contains(['`', '['])
+            || line[..token.start()].matches('`').count() % 2 == 1
+        {
+            continue;
+        }
+        let (pre, core, post) = split_punctuation(token.as_str());
+        let before = n.checked_sub(1).map(|b| tokens[b].as_str());
+        let after = tokens.get(n + 1).map(|a| a.as_str());
+
+        let rule = TICK_RULES
+            .iter()
+            .find(|rule| rule.pattern.is_match(core))
+            .filter(|rule| match rule.context {
+                Context::Anywhere => true,
+                Context::Value => in_code_context(before, after),
+                Context::Code => near_code(before, after),
+            });
+        let ticked = match rule {
+            Some(rule) if rule.kind == TokenKind::Primitive && link_primitives => {
+                match core.trim_start_matches('&') {
+                    "()" => format!("[`{}`](unit)", core),
+                    page if page != core => format!("[`{}`]({})", core, page),
+                    _ => format!("[`{}`]", core),
+                }
+            }
+            Some(_) => format!("`{}`", core),
+            None => continue,
+        };
+        out.push_str(&line[last..token.start()]);
+        out.push_str(&format!("{}{}{}", pre, ticked, post));
+        last = token.end();
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+lazy_static! {
+    static ref TOKEN: Regex = Regex::new(r"\S+").unwrap();
+}
+
+/// Splits the punctuation either side off of `token`, brackets only when they're unbalanced or
+/// wrap the whole token so `()` and `Some(x)` stay whole.
+fn split_punctuation(token: &str) -> (&str, &str, &str) {
+    let balance = |s: &str| s.matches('(').count() as isize - s.matches(')').count() as isize;
+    let mut start = 0;
+    let mut end = token.len();
+
+    loop {
+        let core = &token[start..end];
+        if core.starts_with(['"', '\'']) || (core.starts_with('(') && balance(core) > 0) {
+            start += 1;
+        } else if core.len() > 2
+            && core.starts_with('(')
+            && core.ends_with(')')
+            && balance(&core[1..core.len() - 1]) == 0
+        {
+            start += 1;
+            end -= 1;
+        } else if core.ends_with(['.', ',', ';', ':', '!', '?', '"', '\''])
+            || (core.ends_with(')') && balance(core) < 0)
+        {
+            end -= 1;
+        } else {
+            break;
+        }
+        if start >= end {
+            return ("", token, "");
+        }
+    }
+    (&token[..start], &token[start..end], &token[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_only_code() {
+        let cases = [
+            (
+                "/// Returns a bool, or (u8) as a &str.",
+                "/// Returns a `bool`, or (`u8`) as a `&str`.",
+            ),
+            (
+                "/// Returns true if it's None, otherwise Some(x).",
+                "/// Returns `true` if it's `None`, otherwise `Some(x)`.",
+            ),
+            (
+                "/// That's true. None of it is from a path to n.",
+                "/// That's true. None of it is from a path to n.",
+            ),
+            (
+                "/// Pass 0u8 or () to `bool`",
+                "/// Pass `0u8` or `()` to `bool`",
+            ),
+            (
+                "/// An async fn can take self or a char, str is a slice.",
+                "/// An async fn can take self or a char, str is a slice.",
+            ),
+            (
+                "/// Takes &self -> Self, and a char -> &str.",
+                "/// Takes `&self` -> `Self`, and a `char` -> `&str`.",
+            ),
+            (
+                "/// Collects into a Vec<T>, or HashMap<K,V>. Less than <5.",
+                "/// Collects into a `Vec<T>`, or `HashMap<K,V>`. Less than <5.",
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(tick_code_tokens(line, false), expected);
+        }
+        assert_eq!(
+            tick_code_tokens("/// A &str or ().", true),
+            "/// A [`&str`](str) or [`()`](unit)."
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//!
+//! Parsing of `use` declarations, trees, aliases and globs included.
+//!
+use super::visibility::Visibility;
+
+/// A single name brought into scope by a `use` declaration.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Import {
+    /// The full path the name is imported from, i.e `["std", "collections", "HashMap"]`.
+    pub path: Vec<String>,
+    /// The `as` alias, if there was one.
+    pub alias: Option<String>,
+    /// Whether this is a `path::*` glob, in which case `path` is the module being globbed.
+    pub glob: bool,
+    /// Whether this is a `pub use` of any kind, `pub(crate)` included.
+    pub is_pub: bool,
+    /// Only a plain `pub use` re-exports outside of the crate.
+    pub visibility: Visibility,
+    pub line_num: usize,
+}
+
+impl Import {
+    /// The name this [`Import`] is known by in the importing module, globs and `as _` imports have
+    /// none.
+    pub fn name(&self) -> Option<&str> {
+        if self.glob {
+            return None;
+        }
+        match &self.alias {
+            Some(alias) if alias == "_" => None,
+            Some(alias) => Some(alias),
+            None => self.path.last().map(|s| s.as_str()),
+        }
+    }
+
+    /// The name as it was originally declared, before any aliasing.
+    pub fn original(&self) -> Option<&str> {
+        self.path.last().map(|s| s.as_str())
+    }
+
+    /// The path this was imported from, `::` separated.
+    pub fn source(&self) -> String {
+        self.path.join("::")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    PathSep,
+    Open,
+    Close,
+    Comma,
+    Star,
+}
+
+/// Parses a full `use` statement, i.e `pub use a::{b, c::{d as e}};`, into every [`Import`] it
+/// declares.
+pub fn parse_use(stmt: &str, line_num: usize) -> Vec<Import> {
+    let stmt = stmt.trim();
+    let visibility = Visibility::parse(stmt);
+    let is_pub = visibility != Visibility::Private;
+    let tree = match stmt.find("use ") {
+        Some(idx) => &stmt[idx + 4..],
+        None => return Vec::new(),
+    };
+    let tree = tree.trim().trim_end_matches(';');
+
+    let mut parser = Parser {
+        tokens: tokenise(tree),
+        pos: 0,
+    };
+    let mut out = Vec::new();
+    parser.tree(&[], &mut out);
+
+    out.iter_mut().for_each(|imp| {
+        imp.is_pub = is_pub;
+        imp.visibility = visibility.clone();
+        imp.line_num = line_num;
+    });
+    out
+}
+
+fn tokenise(tree: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = tree.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                tokens.push(Token::PathSep);
+            }
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '*' => tokens.push(Token::Star),
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if n.is_alphanumeric() || n == '_' || n == '#' {
+                        ident.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident.trim_start_matches("r#").to_string()));
+            }
+            _ => (),
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Walks a single use-tree, pushing an [`Import`] for every leaf.
+    fn tree(&mut self, prefix: &[String], out: &mut Vec<Import>) {
+        let mut path = prefix.to_vec();
+        loop {
+            match self.next() {
+                Some(Token::PathSep) => continue,
+                Some(Token::Star) => {
+                    out.push(Import {
+                        path,
+                        glob: true,
+                        ..Default::default()
+                    });
+                    return;
+                }
+                Some(Token::Open) => {
+                    loop {
+                        if matches!(self.peek(), Some(Token::Close)) {
+                            self.next();
+                            break;
+                        }
+                        self.tree(&path, out);
+                        match self.next() {
+                            Some(Token::Comma) => continue,
+                            _ => break,
+                        }
+                    }
+                    return;
+                }
+                Some(Token::Ident(ident)) => {
+                    path.push(ident.to_owned());
+                    match self.peek() {
+                        Some(Token::PathSep) => continue,
+                        Some(Token::Ident(kw)) if kw == "as" => {
+                            self.next();
+                            let alias = match self.next() {
+                                Some(Token::Ident(alias)) => Some(alias.to_owned()),
+                                _ => None,
+                            };
+                            out.push(leaf(path, alias));
+                        }
+                        _ => out.push(leaf(path, None)),
+                    }
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+/// `use foo::{self}` imports `foo` itself.
+fn leaf(mut path: Vec<String>, alias: Option<String>) -> Import {
+    if path.len() > 1 && path.last().map(|s| s == "self").unwrap_or(false) {
+        path.pop();
+    }
+    Import {
+        path,
+        alias,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_trees_and_aliases() {
+        let imports = parse_use("use a::{b, c::{d as e, self}, f::*};", 0);
+        let got = imports
+            .iter()
+            .map(|i| (i.source(), i.name().map(|s| s.to_string()), i.glob))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            got,
+            vec![
+                ("a::b".into(), Some("b".into()), false),
+                ("a::c::d".into(), Some("e".into()), false),
+                ("a::c".into(), Some("c".into()), false),
+                ("a::f".into(), None, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn visibility_and_underscore() {
+        let imports = parse_use("pub(crate) use std::io::Write as _;", 3);
+        assert_eq!(imports.len(), 1);
+        assert!(imports[0].is_pub);
+        assert_eq!(imports[0].line_num, 3);
+        assert_eq!(imports[0].name(), None);
+        assert_eq!(imports[0].original(), Some("Write"));
+    }
+}
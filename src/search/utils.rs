@@ -1,15 +1,26 @@
-use super::consts::*;
+use super::{
+    confidence::{self, WordUsage},
+    config::{Config, LinkPolicy, PrivateLinks, SelfLinks},
+    consts::*,
+    docs::{find_doc_blocks, DocBlock},
+    replace::protected_ranges,
+    rules::{Rule, RuleContext, SpanEdit},
+    symbols::{display_path, resolves_member, LinkTarget, Symbol},
+    uses::{parse_use, Import},
+    visibility::Visibility,
+};
 
 use anyhow::Result;
 use core::fmt::Display;
 use glob::glob;
 use log::debug;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
     hash::Hash,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 /// Are all the available changes to a line done? not done? etc.
@@ -23,7 +34,7 @@ pub enum Linked {
     Unprocessed,
 }
 /// A way to describe lines of code based on what they are/do etc.
-#[derive(PartialEq, Default, Debug, Clone, Hash)]
+#[derive(PartialEq, Eq, Default, Debug, Clone, Hash)]
 #[allow(non_camel_case_types)]
 pub enum Flavour {
     RUST_DOCS,
@@ -34,9 +45,43 @@ pub enum Flavour {
     RUST_IMPORT,
     RUST_USE,
     RUST_TRAIT,
+    RUST_MACRO,
+    RUST_CONST,
+    RUST_STATIC,
+    RUST_UNION,
+    RUST_MOD,
+    RUST_IMPL,
     #[default]
     Tasteless,
 }
+
+impl Flavour {
+    /// The form an ident of this flavour should take when linked, i.e macros are linked as
+    /// [`my_macro!`] rather than [`my_macro`].
+    pub fn linkable(&self, ident: String) -> String {
+        match self {
+            Flavour::RUST_MACRO => format!("{}!", ident),
+            _ => ident,
+        }
+    }
+
+    /// The prefix rust-doc gives the html page of an item of this flavour, i.e `struct` for
+    /// `struct.Foo.html`, modules get an `index.html` of their own instead.
+    pub fn html_kind(&self) -> Option<&'static str> {
+        match self {
+            Flavour::RUST_STRUCT => Some("struct"),
+            Flavour::RUST_ENUM => Some("enum"),
+            Flavour::RUST_TRAIT => Some("trait"),
+            Flavour::RUST_FN => Some("fn"),
+            Flavour::RUST_TY => Some("type"),
+            Flavour::RUST_MACRO => Some("macro"),
+            Flavour::RUST_CONST => Some("constant"),
+            Flavour::RUST_STATIC => Some("static"),
+            Flavour::RUST_UNION => Some("union"),
+            _ => None,
+        }
+    }
+}
 /// A line from a source file exactly as is.
 #[derive(Default, Debug, Clone, Hash)]
 pub struct RawLine {
@@ -46,14 +91,119 @@ pub struct RawLine {
     pub flavour: Flavour,
     pub idents: Vec<String>,
     pub source_file: PathBuf,
+    /// The attributes applied to the item declared on this line, i.e `#[cfg(test)]`.
+    pub attrs: Vec<String>,
+    /// Only compiled for tests, a `#[test]` or anything inside a `#[cfg(test)]` item.
+    pub test_only: bool,
+    /// `#[doc(hidden)]`, or inside something that is.
+    pub hidden: bool,
+    /// The visibility the item on this line was declared with.
+    pub visibility: Visibility,
+    /// The inline `mod`s this line is nested in.
+    pub scope: Vec<String>,
+    /// Inside a fn body, where nothing is reachable from outside.
+    pub in_body: bool,
+    /// Declared in an `impl` or `trait`, a method say, which no `module::path` reaches.
+    pub associated: bool,
+    /// The type, trait or enum an associated item, or a variant, on this line belongs to.
+    pub owner: Option<String>,
+    /// The name the associated item or variant is declared with, linkable or not, i.e `new`.
+    pub member: Option<String>,
+    /// Actually reachable from outside of the crate, see [`SourceTree::compute_visibility`].
+    pub effective_pub: bool,
+}
+
+/// An item whose `{}` body we're inside of, see [`RawSourceCode::mark_attributes`].
+#[derive(Debug)]
+struct Enclosing {
+    depth: usize,
+    entered: bool,
+    flavour: Flavour,
+    name: Option<String>,
+    visibility: Visibility,
+    trait_impl: bool,
+    test_only: bool,
+    hidden: bool,
 }
 
 /// A line from a source file with its contents modified by this app.
-#[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Hash)]
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct AdjustedLine {
     pub line_num: usize,
     pub contents: String,
     pub source_file: PathBuf,
+    /// What made the changes, and why.
+    pub edits: Vec<SpanEdit>,
+}
+
+impl AdjustedLine {
+    /// Its [`AdjustedLine::edits`] as edits of `old`, the line as it was, rather than of the line
+    /// as each rule found it. Edits touching another's change are combined with it.
+    pub fn original_edits(&self, old: &str) -> Vec<SpanEdit> {
+        // Each change so far, by its range in `old` and in the line as it is by then.
+        let mut changes: Vec<(Range<usize>, Range<usize>, Vec<&SpanEdit>)> = Vec::new();
+        // A rule's edits were made last first, so the ranges of those before stayed put.
+        for edit in self
+            .edits
+            .chunk_by(|a, b| a.rule == b.rule)
+            .flat_map(|made| made.iter().rev())
+        {
+            let r = edit.range.clone();
+            let (hit, rest): (Vec<_>, Vec<_>) = changes
+                .into_iter()
+                .partition(|(_, now, _)| now.start < r.end && r.start < now.end);
+            let start = hit
+                .iter()
+                .map(|(_, now, _)| now.start)
+                .fold(r.start, usize::min);
+            let end = hit
+                .iter()
+                .map(|(_, now, _)| now.end)
+                .fold(r.end, usize::max);
+            let before = rest.iter().filter(|(_, now, _)| now.end <= start);
+            let old_start = start + before.clone().map(|(was, _, _)| was.len()).sum::<usize>()
+                - before.map(|(_, now, _)| now.len()).sum::<usize>();
+            let old_end =
+                old_start + (end - start) + hit.iter().map(|(was, _, _)| was.len()).sum::<usize>()
+                    - hit.iter().map(|(_, now, _)| now.len()).sum::<usize>();
+            let shift = |p: usize| p + edit.replacement.len() - r.len();
+
+            changes = rest
+                .into_iter()
+                .map(|(was, now, by)| match now.start >= end {
+                    true => (was, shift(now.start)..shift(now.end), by),
+                    false => (was, now, by),
+                })
+                .collect();
+            let mut by = hit
+                .into_iter()
+                .flat_map(|(_, _, by)| by)
+                .collect::<Vec<_>>();
+            by.push(edit);
+            changes.push((old_start..old_end, start..shift(end), by));
+        }
+        changes.sort_by_key(|(was, _, _)| was.start);
+
+        changes
+            .into_iter()
+            .filter(|(was, _, _)| old.get(was.clone()).is_some())
+            .map(|(was, now, by)| {
+                let mut rules = by.iter().map(|e| e.rule.as_str()).collect::<Vec<&str>>();
+                rules.dedup();
+                SpanEdit {
+                    line_num: self.line_num,
+                    range: was,
+                    replacement: self.contents[now].to_string(),
+                    rule: rules.join(", "),
+                    message: by
+                        .iter()
+                        .map(|e| e.message.as_str())
+                        .collect::<Vec<&str>>()
+                        .join("; "),
+                }
+            })
+            .collect()
+    }
 }
 
 impl From<RawLine> for AdjustedLine {
@@ -62,6 +212,7 @@ impl From<RawLine> for AdjustedLine {
             line_num: line.line_num,
             contents: line.contents,
             source_file: line.source_file,
+            edits: Vec::new(),
         }
     }
 }
@@ -69,13 +220,34 @@ impl From<RawLine> for AdjustedLine {
 /// All the source code combined!
 #[derive(Default, Debug, Clone)]
 pub struct SourceTree {
+    /// The name of the crate these are the sources of, when known.
+    pub crate_name: Option<String>,
+    /// The crate's directory, where its `Cargo.toml` is, when known.
+    pub root: Option<PathBuf>,
     pub source_files: Vec<RawSourceCode>,
     pub named_idents: Vec<String>,
+    pub symbols: Vec<Symbol>,
+    /// What [`SourceTree::add_targets`] made linkable from outside of the tree, i.e `std` and
+    /// dependencies, kept to carry over to a [`SourceTree::with_sources`].
+    pub external_targets: HashMap<String, LinkTarget>,
 }
 
 impl SourceTree {
     /// Populates the idents we care about...
     fn populate_idents(mut self) -> Self {
+        self.exclude_test_files();
+        self.compute_visibility();
+        self.expand_globs();
+        self.add_canonical_targets();
+        let usage = Arc::new(WordUsage::from_lines(
+            self.source_files
+                .iter()
+                .flat_map(|sf| sf.doc_lines())
+                .map(|rl| rl.contents.as_str()),
+        ));
+        self.source_files
+            .iter_mut()
+            .for_each(|sf| sf.word_usage = usage.clone());
         self.source_files.iter().for_each(|sf| {
             sf.named_idents
                 .iter()
@@ -96,7 +268,7 @@ impl SourceTree {
     pub fn new_from_paths(paths: &[String]) -> Self {
         SourceTree {
             source_files: paths.iter().map(RawSourceCode::new_from_file).collect(),
-            named_idents: Vec::new(),
+            ..Default::default()
         }
         .populate_idents()
     }
@@ -112,25 +284,437 @@ impl SourceTree {
     where
         P: Display + AsRef<Path>,
     {
-        let search_path = format!("{}/**/*.rs", dir);
+        Self::new_from_files(&find_source_files(dir))
+    }
+    /// Creates a new [`SourceTree`] from exactly these files.
+    pub fn new_from_files(files: &[PathBuf]) -> Self {
         SourceTree {
-            source_files: {
-                glob(&search_path)
-                    .unwrap()
-                    .filter_map(Result::ok)
-                    .filter(|f| !f.display().to_string().contains("target/"))
-                    .map(|p| RawSourceCode::new_from_file(&p))
-                    .collect::<Vec<RawSourceCode>>()
-            },
-            named_idents: Vec::new(),
+            source_files: files.iter().map(RawSourceCode::new_from_file).collect(),
+            ..Default::default()
         }
         .populate_idents()
     }
+    /// This tree rebuilt from `source_files`, i.e with some of them re-read after a change, without
+    /// reading the rest or indexing `std` and dependencies all over again.
+    pub fn with_sources(&self, source_files: Vec<RawSourceCode>) -> Self {
+        let mut tree = SourceTree {
+            crate_name: self.crate_name.clone(),
+            root: self.root.clone(),
+            source_files,
+            ..Default::default()
+        }
+        .populate_idents();
+        tree.add_targets(&self.external_targets);
+        tree
+    }
+    /// This tree with `contents` in place of what's on disk for `file`, i.e an editor's unsaved
+    /// buffer, `file` is added if it isn't one of the tree's yet.
+    pub fn with_contents(&self, file: &Path, contents: &str) -> Self {
+        let mut sources = self
+            .source_files
+            .iter()
+            .map(|rsc| match rsc.file == file {
+                true => RawSourceCode::new_from_contents(file, contents),
+                false => RawSourceCode::new_from_file(&rsc.file),
+            })
+            .collect::<Vec<RawSourceCode>>();
+        if !sources.iter().any(|rsc| rsc.file == file) {
+            sources.push(RawSourceCode::new_from_contents(file, contents));
+        }
+        self.with_sources(sources)
+    }
+    /// This tree with `rsc` in place of what was read from `file`, or without `file` when it's
+    /// `None`, working out only that file's symbols and link targets again. What spans files, a
+    /// `pub use` of one of its items say, stays as it was until the tree is indexed again. Returns
+    /// the idents whose items changed, which other files may link differently now.
+    pub fn replace_file(&mut self, file: &Path, rsc: Option<RawSourceCode>) -> HashSet<String> {
+        let test_mods = self.test_mods();
+        let mut exports = self.glob_exports();
+        exports.remove(&module_path_of(file));
+        let usage = self
+            .source_files
+            .first()
+            .map(|sf| sf.word_usage.clone())
+            .unwrap_or_default();
+        let before = self.public_targets("crate", LinkTarget::Explicit);
+
+        let mut single = SourceTree {
+            source_files: rsc.into_iter().collect(),
+            ..Default::default()
+        };
+        single.exclude_test_mods(&test_mods);
+        single.compute_visibility();
+        exports.extend(single.glob_exports());
+        single.expand_globs_from(&exports);
+
+        // Moving an item down a line doesn't change it.
+        let key = |sym: &Symbol| {
+            (
+                sym.ident.clone(),
+                sym.flavour.clone(),
+                sym.path.clone(),
+                sym.public_paths.clone(),
+            )
+        };
+        let (old, kept): (Vec<Symbol>, Vec<Symbol>) = std::mem::take(&mut self.symbols)
+            .into_iter()
+            .partition(|sym| sym.source_file == file);
+        let old_keys = old.iter().map(key).collect::<Vec<_>>();
+        let new_keys = single.symbols.iter().map(key).collect::<Vec<_>>();
+        let changed = old_keys
+            .iter()
+            .filter(|k| !new_keys.contains(k))
+            .chain(new_keys.iter().filter(|k| !old_keys.contains(k)))
+            .map(|(ident, _, _, _)| ident.clone())
+            .collect::<HashSet<String>>();
+        self.symbols = kept;
+        self.symbols.append(&mut single.symbols);
+
+        // The other files only lose, or gain, the canonical targets of what changed.
+        let after = self.public_targets("crate", LinkTarget::Explicit);
+        let only_changed = |targets: &HashMap<String, LinkTarget>| {
+            targets
+                .iter()
+                .filter(|(ident, _)| changed.contains(*ident))
+                .map(|(ident, target)| (ident.clone(), target.clone()))
+                .collect::<HashMap<String, LinkTarget>>()
+        };
+        let (gained, external) = (only_changed(&after), only_changed(&self.external_targets));
+        for sf in self.source_files.iter_mut().filter(|sf| sf.file != file) {
+            for (ident, target) in before.iter().filter(|(ident, _)| changed.contains(*ident)) {
+                if sf.link_targets.get(ident) == Some(target) {
+                    sf.link_targets.remove(ident);
+                    sf.named_idents.retain(|named| named != ident);
+                }
+            }
+            sf.insert_targets(&gained);
+            sf.insert_targets(&external);
+        }
+
+        let existing = self.source_files.iter().position(|sf| sf.file == file);
+        match (existing, single.source_files.pop()) {
+            (n, Some(mut sf)) => {
+                sf.word_usage = usage;
+                sf.insert_targets(&after);
+                sf.insert_targets(&self.external_targets);
+                match n {
+                    Some(n) => self.source_files[n] = sf,
+                    None => self.source_files.push(sf),
+                }
+            }
+            (Some(n), None) => {
+                self.source_files.remove(n);
+            }
+            (None, None) => {}
+        }
+        self.collect_named_idents();
+        changed
+    }
+    /// Files that are only compiled for tests, those declared with `#[cfg(test)] mod tests;` and
+    /// integration tests/benches, contribute nothing to link to.
+    fn exclude_test_files(&mut self) {
+        let test_mods = self.test_mods();
+        self.exclude_test_mods(&test_mods);
+    }
+
+    /// The paths of the modules declared `#[cfg(test)]`.
+    fn test_mods(&self) -> Vec<Vec<String>> {
+        self.source_files
+            .iter()
+            .flat_map(|sf| {
+                sf.values()
+                    .filter(|rl| rl.flavour == Flavour::RUST_MOD && rl.test_only)
+                    .flat_map(|rl| rl.idents.iter())
+                    .map(|ident| {
+                        let mut path = sf.module_path.clone();
+                        path.push(ident.to_owned());
+                        path
+                    })
+            })
+            .collect()
+    }
+
+    fn exclude_test_mods(&mut self, test_mods: &[Vec<String>]) {
+        self.source_files
+            .iter_mut()
+            .filter(|sf| {
+                sf.is_test_file() || test_mods.iter().any(|m| sf.module_path.starts_with(m))
+            })
+            .for_each(|sf| {
+                sf.values_mut().for_each(|rl| rl.test_only = true);
+                sf.named_idents.clear();
+                sf.private_idents.clear();
+            });
+    }
+
+    /// Works out which items are actually reachable from outside of the crate, a `pub` item in a
+    /// private module isn't unless it's re-exported with a `pub use` somewhere that is.
+    fn compute_visibility(&mut self) {
+        let mut mods: HashMap<Vec<String>, Visibility> = HashMap::new();
+        for sf in self.source_files.iter() {
+            mods.entry(sf.module_path.clone())
+                .or_insert(Visibility::Public);
+            for rl in sf.values().filter(|rl| rl.flavour == Flavour::RUST_MOD) {
+                let mut path = sf.module_path.clone();
+                path.extend(rl.scope.iter().cloned());
+                path.extend(rl.idents.iter().cloned());
+                let vis = match rl.in_body || rl.test_only {
+                    true => Visibility::Private,
+                    false => rl.visibility.clone(),
+                };
+                mods.insert(path, vis);
+            }
+        }
+        mods.insert(Vec::new(), Visibility::Public);
+
+        // Modules we know nothing of (i.e their parent wasn't part of the tree) are given the
+        // benefit of the doubt.
+        let reachable = |module: &[String]| {
+            (1..=module.len()).all(|i| {
+                mods.get(&module[..i])
+                    .map(|v| v.is_public())
+                    .unwrap_or(true)
+            })
+        };
+
+        // Every `pub use`, public or otherwise as chains can pass through private modules, keyed
+        // by the path it creates and whether that path is itself public.
+        let mut aliases: HashMap<Vec<String>, (Vec<String>, bool)> = HashMap::new();
+        let mut globs: Vec<(Vec<String>, Vec<String>, bool)> = Vec::new();
+        for sf in self.source_files.iter() {
+            for imp in sf.imports.iter().filter(|imp| imp.is_pub) {
+                let mut here = sf.module_path.clone();
+                if let Some(rl) = sf.get(&imp.line_num) {
+                    if rl.test_only {
+                        continue;
+                    }
+                    here.extend(rl.scope.iter().cloned());
+                }
+                let public = imp.visibility.is_public() && reachable(&here);
+
+                if imp.glob {
+                    if let Some(module) = resolve_module(&here, &imp.path, &mods) {
+                        globs.push((here, module, public));
+                    }
+                } else if let (Some((last, parent)), Some(name)) =
+                    (imp.path.split_last(), imp.name())
+                {
+                    if let Some(mut target) = resolve_module(&here, parent, &mods) {
+                        target.push(last.to_owned());
+                        let mut path = here.clone();
+                        path.push(name.to_string());
+                        aliases.insert(path, (target, public));
+                    }
+                }
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for sf in self.source_files.iter() {
+            for rl in sf.values().filter(|rl| rl.declares() && !rl.test_only) {
+                let mut module = sf.module_path.clone();
+                module.extend(rl.scope.iter().cloned());
+                let declared = !rl.is_private() && !rl.in_body && !rl.hidden;
+
+                for (ident, path) in rl.declared_paths(&module) {
+                    let name = path.last().cloned().unwrap_or_default();
+
+                    let mut public_paths = Vec::new();
+                    if declared && rl.flavour == Flavour::RUST_MACRO {
+                        // #[macro_export] puts macros at the crate root regardless.
+                        public_paths.push(vec![name.clone()]);
+                    } else if declared && reachable(&module) {
+                        public_paths.push(path.clone());
+                    }
+                    // Associated items are only ever reached through what they belong to.
+                    if declared && !rl.associated {
+                        globs
+                            .iter()
+                            .filter(|(_, globbed, public)| *public && *globbed == module)
+                            .for_each(|(here, _, _)| {
+                                let mut via = here.clone();
+                                via.push(name.clone());
+                                public_paths.push(via);
+                            });
+                    }
+                    if !rl.hidden && !rl.in_body && !rl.associated {
+                        aliases
+                            .iter()
+                            .filter(|(_, (_, public))| *public)
+                            .filter(|(_, (target, _))| chase(target, &aliases) == path)
+                            .for_each(|(via, _)| public_paths.push(via.clone()));
+                    }
+                    public_paths.sort();
+                    public_paths.dedup();
+
+                    symbols.push(Symbol {
+                        ident,
+                        flavour: rl.flavour.clone(),
+                        path,
+                        public_paths,
+                        associated: rl.associated,
+                        owner: rl.owner.clone(),
+                        source_file: sf.file.clone(),
+                        line_num: rl.line_num,
+                    });
+                }
+            }
+        }
+
+        let item_pub = symbols
+            .iter()
+            .map(|sym| (sym.path.clone(), sym.is_public()))
+            .collect::<HashMap<Vec<String>, bool>>();
+        for sf in self.source_files.iter_mut() {
+            let mut module = sf.module_path.clone();
+            for rl in
+                sf.m.values_mut()
+                    .filter(|rl| rl.declares() && !rl.test_only)
+            {
+                module.truncate(sf.module_path.len());
+                module.extend(rl.scope.iter().cloned());
+                rl.effective_pub = rl
+                    .declared_paths(&module)
+                    .iter()
+                    .any(|(_, path)| item_pub.get(path) == Some(&true));
+            }
+        }
+        self.symbols = symbols;
+
+        for sf in self.source_files.iter_mut() {
+            sf.collect_private_idents();
+
+            // Names imported from elsewhere in the crate are as private as what they name.
+            let private_imports = sf
+                .imports
+                .iter()
+                .filter(|imp| !imp.glob)
+                .filter_map(|imp| {
+                    let (last, parent) = imp.path.split_last()?;
+                    let mut path = resolve_module(&sf.module_path, parent, &mods)?;
+                    path.push(last.to_owned());
+                    match item_pub.get(&chase(&path, &aliases)) {
+                        Some(false) => imp.name().map(|s| s.to_string()),
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<String>>();
+            sf.private_idents.extend(private_imports);
+
+            let module_reachable = reachable(&sf.module_path);
+            sf.mark_public_docs(module_reachable);
+        }
+    }
+
+    /// Public items that aren't in scope in a file can still be linked from it, explicitly, through
+    /// their canonical path. Names shared by more than one public item are left alone.
+    fn add_canonical_targets(&mut self) {
+        let targets = self.public_targets("crate", LinkTarget::Explicit);
+        self.insert_targets(&targets);
+    }
+
+    /// The [`LinkTarget`] of each public item, reached from `root`, items sharing a name are left
+    /// out as there's no telling which is meant.
+    pub fn public_targets(
+        &self,
+        root: &str,
+        make: fn(String) -> LinkTarget,
+    ) -> HashMap<String, LinkTarget> {
+        self.targets_by(|sym| sym.path_from(root).map(make))
+    }
+
+    /// The [`LinkTarget`] `make` gives each public item, items sharing a name are left out and so
+    /// are associated items, there's no path to link them by.
+    pub fn targets_by<F>(&self, make: F) -> HashMap<String, LinkTarget>
+    where
+        F: Fn(&Symbol) -> Option<LinkTarget>,
+    {
+        let mut targets: HashMap<String, Option<LinkTarget>> = HashMap::new();
+        for sym in self.symbols.iter().filter(|sym| !sym.associated) {
+            if let Some(target) = make(sym) {
+                targets
+                    .entry(sym.ident.clone())
+                    .and_modify(|t| {
+                        if t.as_ref() != Some(&target) {
+                            *t = None
+                        }
+                    })
+                    .or_insert(Some(target));
+            }
+        }
+        targets
+            .into_iter()
+            .filter_map(|(ident, target)| target.map(|t| (ident, t)))
+            .collect()
+    }
+
+    /// Makes `targets` linkable from every file they aren't already in scope in.
+    pub fn add_targets(&mut self, targets: &HashMap<String, LinkTarget>) {
+        for (ident, target) in targets.iter() {
+            self.external_targets
+                .entry(ident.to_owned())
+                .or_insert_with(|| target.clone());
+        }
+        self.insert_targets(targets);
+    }
+
+    fn insert_targets(&mut self, targets: &HashMap<String, LinkTarget>) {
+        for sf in self.source_files.iter_mut() {
+            sf.insert_targets(targets);
+        }
+        self.collect_named_idents();
+    }
+
+    fn collect_named_idents(&mut self) {
+        self.named_idents = self
+            .source_files
+            .iter()
+            .flat_map(|sf| sf.named_idents.iter().cloned())
+            .collect();
+    }
+
+    /// Expands `use some::module::*` imports against the modules of this tree, globs of anything
+    /// outside of it (`std::*` etc) can't be expanded and are left alone.
+    fn expand_globs(&mut self) {
+        let exports = self.glob_exports();
+        self.expand_globs_from(&exports);
+    }
+
+    /// What `use module::*` gets from each module of this tree.
+    fn glob_exports(&self) -> HashMap<Vec<String>, Vec<(String, bool)>> {
+        self.source_files
+            .iter()
+            .map(|sf| (sf.module_path.clone(), sf.glob_exports()))
+            .collect()
+    }
+
+    fn expand_globs_from(&mut self, exports: &HashMap<Vec<String>, Vec<(String, bool)>>) {
+        self.source_files.iter_mut().for_each(|sf| {
+            let globbed = sf
+                .imports
+                .iter()
+                .filter(|imp| imp.glob)
+                .filter_map(|imp| resolve_module(&sf.module_path, &imp.path, exports))
+                .filter_map(|module| exports.get(&module))
+                .flatten()
+                .cloned()
+                .collect::<Vec<(String, bool)>>();
+
+            globbed.into_iter().for_each(|(ident, public)| {
+                if !sf.named_idents.contains(&ident) {
+                    if !public {
+                        sf.private_idents.insert(ident.clone());
+                    }
+                    sf.named_idents.push(ident);
+                }
+            });
+        });
+    }
     /// Commits changes to disk, essentially writing the [`AdjustedLine`] back to a `Result` of
     /// the same name, line-by-line.
     pub fn write_changes(file: PathBuf, changes: &mut [AdjustedLine], write_flag: bool) {
         debug!("SourceTree::write_changes was called");
-        changes.sort_by(|a, b| a.line_num.cmp(&b.line_num));
+        changes.sort_by_key(|a| a.line_num);
         let output: Vec<String> = changes
             .iter_mut()
             .map(|adj_line| adj_line.contents.to_owned())
@@ -153,6 +737,18 @@ pub struct RawSourceCode {
     pub doc_locs: Vec<usize>,
     pub total_lines: usize,
     pub named_idents: Vec<String>,
+    /// The module this file is, i.e `src/search/utils.rs` is `["search", "utils"]`.
+    pub module_path: Vec<String>,
+    pub imports: Vec<Import>,
+    /// Idents which must be linked with an explicit destination, i.e a name only in scope
+    /// through its alias: `HashMap` -> `Map` for `use std::collections::HashMap as Map;`.
+    pub link_targets: HashMap<String, LinkTarget>,
+    pub doc_blocks: Vec<DocBlock>,
+    /// Idents only declared privately in this file, these aren't linked unless
+    /// [`Config::document_private_items`] is set.
+    pub private_idents: HashSet<String>,
+    /// How words are written across the docs, of the whole tree once it's known.
+    pub word_usage: Arc<WordUsage>,
 }
 
 impl RawSourceCode {
@@ -161,59 +757,404 @@ impl RawSourceCode {
         PathBuf: From<P>,
         P: AsRef<Path> + Copy,
     {
+        let contents = fs::read(file.as_ref()).unwrap_or_default();
+        Self::new_from_contents(file, &String::from_utf8_lossy(&contents))
+    }
+
+    /// Like [`RawSourceCode::new_from_file`], with `contents` in place of what's on disk, i.e an
+    /// editor's unsaved changes.
+    pub fn new_from_contents<P>(file: P, contents: &str) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let file = file.as_ref();
         let mut raw_source_file = RawSourceCode {
             m: HashMap::new(),
-            file: PathBuf::from(file),
+            file: file.to_path_buf(),
             ident_locs: Vec::new(),
             doc_locs: Vec::new(),
             total_lines: 0,
             named_idents: Vec::new(),
+            module_path: module_path_of(file),
+            imports: Vec::new(),
+            link_targets: HashMap::new(),
+            doc_blocks: Vec::new(),
+            private_idents: HashSet::new(),
+            word_usage: Arc::default(),
         };
 
-        if let Ok(lines) = crate::read_lines(file) {
-            lines
-                .collect::<Vec<_>>()
-                .iter()
-                .enumerate()
-                .for_each(|(e, l)| {
-                    if let Ok(l) = l {
-                        let mut raw_line = RawLine {
-                            all_linked: Linked::Unprocessed,
-                            contents: l.into(),
-                            line_num: e,
-                            source_file: file.into(),
-                            ..Default::default()
-                        };
-                        raw_line.find_docs();
-                        raw_line.find_idents();
-                        raw_source_file
-                            .named_idents
-                            .extend(raw_line.idents.iter().cloned());
-                        raw_source_file.m.insert(e, raw_line);
-                    }
-                });
-        }
+        // `use` statements can span many lines, so they're collected until their ';'.
+        let mut pending_use: Option<(usize, String)> = None;
+
+        contents.lines().enumerate().for_each(|(e, l)| {
+            let mut raw_line = RawLine {
+                all_linked: Linked::Unprocessed,
+                contents: l.into(),
+                line_num: e,
+                source_file: file.to_path_buf(),
+                ..Default::default()
+            };
+            raw_line.find_docs();
+
+            if pending_use.is_some() {
+                raw_line.flavour = Flavour::RUST_IMPORT;
+            } else if raw_line.flavour != Flavour::RUST_DOCS
+                && RUST_USE.is_match(&raw_line.contents)
+            {
+                raw_line.flavour = Flavour::RUST_USE;
+                pending_use = Some((e, String::new()));
+            } else {
+                raw_line.find_idents();
+            }
+
+            if let Some((start, mut stmt)) = pending_use.take() {
+                stmt.push_str(&raw_line.contents);
+                stmt.push(' ');
+                if raw_line.contents.contains(';') {
+                    raw_source_file.imports.extend(parse_use(&stmt, start));
+                } else {
+                    pending_use = Some((start, stmt));
+                }
+            }
+
+            raw_source_file.m.insert(e, raw_line);
+        });
         raw_source_file.total_lines = raw_source_file.m.len();
+        raw_source_file.mark_attributes();
+        raw_source_file.add_declared_idents();
+        raw_source_file.add_imported_idents();
+        raw_source_file.doc_blocks = find_doc_blocks(
+            &raw_source_file.m,
+            raw_source_file.total_lines,
+            raw_source_file.module_path.last().map(|s| s.as_str()),
+        );
+        raw_source_file.mark_public_docs(true);
+        raw_source_file.word_usage = Arc::new(WordUsage::from_lines(
+            raw_source_file.doc_lines().map(|rl| rl.contents.as_str()),
+        ));
         raw_source_file.named_idents.dedup();
         raw_source_file.named_idents.retain(|x| !x.is_empty());
         raw_source_file
     }
 
-    /// Checks whether `self` [`should_be_modified`] and if so, [`process`] `Result` the passed
-    /// `idents` is called.
-    pub fn make_adjustments(&self, idents: &[String]) -> Vec<AdjustedLine> {
-        self.m
+    /// Walks the file applying attributes to the items they're on, and working out what each item
+    /// is nested in: which items are only compiled for tests or are hidden (either directly or by
+    /// being inside an item that is), which inline modules they're in and which are in fn bodies.
+    fn mark_attributes(&mut self) {
+        let mut depth = 0;
+        let mut in_str = false;
+        let mut attrs: Vec<String> = Vec::new();
+        let mut in_attr = false;
+        let mut stack: Vec<Enclosing> = Vec::new();
+
+        for n in 0..self.total_lines {
+            let rl = match self.m.get_mut(&n) {
+                Some(rl) if rl.flavour != Flavour::RUST_DOCS => rl,
+                _ => continue,
+            };
+            let text = rl.contents.trim().to_string();
+
+            if !in_str && (in_attr || text.starts_with("#[")) {
+                match (in_attr, attrs.last_mut()) {
+                    (true, Some(attr)) => attr.push_str(&text),
+                    _ => attrs.push(text.clone()),
+                }
+                in_attr = !text.ends_with(']');
+                continue;
+            }
+
+            if !in_str && rl.is_item() {
+                rl.attrs = std::mem::take(&mut attrs);
+                rl.test_only = stack.iter().any(|e| e.test_only)
+                    || rl.attrs.iter().any(|a| RUST_TEST_ATTR.is_match(a));
+                rl.hidden = stack.iter().any(|e| e.hidden)
+                    || rl.attrs.iter().any(|a| RUST_DOC_HIDDEN.is_match(a));
+                rl.scope = stack
+                    .iter()
+                    .filter(|e| e.flavour == Flavour::RUST_MOD)
+                    .filter_map(|e| e.name.clone())
+                    .collect();
+                rl.in_body = stack.iter().any(|e| e.flavour == Flavour::RUST_FN);
+                rl.associated = matches!(
+                    stack.last().map(|e| &e.flavour),
+                    Some(Flavour::RUST_TRAIT | Flavour::RUST_IMPL)
+                );
+                // Trait items are as visible as their trait, trait impls as the trait.
+                rl.visibility = match stack.last() {
+                    Some(e) if e.flavour == Flavour::RUST_TRAIT => e.visibility.clone(),
+                    Some(e) if e.trait_impl => Visibility::Public,
+                    _ => Visibility::parse(&rl.contents),
+                };
+                if rl.associated {
+                    rl.owner = stack.last().and_then(|e| e.name.clone());
+                    rl.member = RUST_MEMBER
+                        .captures(&rl.contents)
+                        .map(|caps| caps["ident"].to_string());
+                }
+
+                if matches!(
+                    rl.flavour,
+                    Flavour::RUST_FN
+                        | Flavour::RUST_MOD
+                        | Flavour::RUST_TRAIT
+                        | Flavour::RUST_IMPL
+                        | Flavour::RUST_ENUM
+                ) && !text.ends_with(';')
+                {
+                    // Impls are named for the type they're on, see [`RUST_IMPL`].
+                    let name = match rl.flavour {
+                        Flavour::RUST_IMPL => RUST_IMPL
+                            .captures(&rl.contents)
+                            .map(|caps| caps["ident"].to_string()),
+                        _ => rl.idents.first().cloned(),
+                    };
+                    stack.push(Enclosing {
+                        depth,
+                        entered: false,
+                        flavour: rl.flavour.clone(),
+                        name,
+                        visibility: rl.visibility.clone(),
+                        trait_impl: rl.flavour == Flavour::RUST_IMPL && text.contains(" for "),
+                        test_only: rl.test_only,
+                        hidden: rl.hidden,
+                    });
+                }
+            } else if !text.is_empty() && !text.starts_with("//") {
+                // Variants are as visible as their enum, and only ever reached through it.
+                if let Some(e) = stack.last().filter(|e| {
+                    e.flavour == Flavour::RUST_ENUM && e.entered && depth == e.depth + 1
+                }) {
+                    if let Some(caps) = RUST_ENUM_VARIANT.captures(&text) {
+                        rl.member = Some(caps["ident"].to_string());
+                        rl.owner = e.name.clone();
+                        rl.associated = true;
+                        rl.visibility = e.visibility.clone();
+                        rl.test_only = stack.iter().any(|e| e.test_only);
+                        rl.hidden = stack.iter().any(|e| e.hidden);
+                        rl.scope = stack
+                            .iter()
+                            .filter(|e| e.flavour == Flavour::RUST_MOD)
+                            .filter_map(|e| e.name.clone())
+                            .collect();
+                    }
+                }
+                attrs.clear();
+            }
+
+            depth = (depth as isize + brace_delta(&text, &mut in_str)).max(0) as usize;
+
+            if let Some(top) = stack.last_mut() {
+                if !top.entered && depth > top.depth {
+                    top.entered = true;
+                } else if !top.entered && (text.ends_with(';') || text.contains('{')) {
+                    // Bodiless declarations and one liners like `fn foo() {}`.
+                    stack.pop();
+                }
+            }
+            while stack.last().map(|e| e.entered && depth <= e.depth) == Some(true) {
+                stack.pop();
+            }
+        }
+    }
+
+    /// The idents of the items declared in this file, minus those only there for tests or hidden.
+    fn add_declared_idents(&mut self) {
+        for n in 0..self.total_lines {
+            let rl = match self.m.get_mut(&n) {
+                Some(rl) if !rl.test_only && !rl.hidden => rl,
+                _ => continue,
+            };
+            // Until the whole tree is known, the best guess is that this file's module is public.
+            rl.effective_pub = !rl.in_body && !rl.is_private();
+            self.named_idents.extend(rl.idents.iter().cloned());
+        }
+        self.collect_private_idents();
+    }
+
+    /// Idents declared here that aren't [`RawLine::effective_pub`], where the same name is declared
+    /// publicly too (say in two inline modules) the public one wins.
+    fn collect_private_idents(&mut self) {
+        let mut public = HashSet::new();
+        self.private_idents.clear();
+
+        for rl in self.m.values().filter(|rl| !rl.test_only && !rl.hidden) {
+            for ident in rl.idents.iter() {
+                if rl.effective_pub {
+                    public.insert(ident.to_owned());
+                } else {
+                    self.private_idents.insert(ident.to_owned());
+                }
+            }
+        }
+        self.private_idents.retain(|ident| !public.contains(ident));
+    }
+
+    /// Docs are public when what they document is, docs we can't place (struct fields, enum
+    /// variants etc) are assumed to be public.
+    fn mark_public_docs(&mut self, module_reachable: bool) {
+        for block in self.doc_blocks.iter_mut() {
+            block.public = if block.inner {
+                module_reachable
+            } else {
+                block
+                    .item_line
+                    .and_then(|n| self.m.get(&n))
+                    .filter(|rl| rl.is_item())
+                    .map(|rl| rl.effective_pub)
+                    .unwrap_or(true)
+            };
+        }
+    }
+
+    /// Integration tests and benches live outside of `src`, in `tests` and `benches`.
+    fn is_test_file(&self) -> bool {
+        let components = self
+            .file
             .iter()
-            .filter(|(_, raw_line)| raw_line.should_be_modified(idents))
-            .map(|(_, f)| -> RawLine { f.to_owned().process_changes(idents) })
-            .map(|rpl| -> AdjustedLine { rpl.into() })
-            .collect::<Vec<AdjustedLine>>()
+            .map(|c| c.to_string_lossy())
+            .collect::<Vec<_>>();
+        !components.iter().any(|c| c == "src")
+            && components.iter().any(|c| c == "tests" || c == "benches")
+    }
+
+    /// Imported names are linkable too, and when aliased the original name is linked through the
+    /// alias as that's the only name rust-doc can see.
+    fn add_imported_idents(&mut self) {
+        for imp in self.imports.iter() {
+            if self
+                .m
+                .get(&imp.line_num)
+                .map(|rl| rl.test_only || rl.hidden)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let name = match imp.name() {
+                Some(name) if name.len() > 2 && !NEVERS.contains(&name) => name.to_string(),
+                _ => continue,
+            };
+            if let Some(original) = imp.original() {
+                if original != name && !NEVERS.contains(&original) {
+                    self.link_targets
+                        .insert(original.to_string(), LinkTarget::Explicit(name.clone()));
+                    self.named_idents.push(original.to_string());
+                }
+            }
+            self.named_idents.push(name);
+        }
+    }
+
+    /// Makes `targets` linkable from this file, those it names already aside.
+    fn insert_targets(&mut self, targets: &HashMap<String, LinkTarget>) {
+        for (ident, target) in targets.iter() {
+            if !self.named_idents.contains(ident) && !NEVERS.contains(&ident.as_str()) {
+                self.named_idents.push(ident.to_owned());
+                self.link_targets.insert(ident.to_owned(), target.clone());
+            }
+        }
+    }
+
+    /// The idents another module gets from `use this::module::*`, its own items and re-exports,
+    /// along with whether each is [`RawLine::effective_pub`].
+    fn glob_exports(&self) -> Vec<(String, bool)> {
+        self.m
+            .values()
+            .filter(|rl| !matches!(rl.flavour, Flavour::RUST_USE | Flavour::RUST_IMPORT))
+            .filter(|rl| !rl.test_only && !rl.hidden && rl.visibility != Visibility::Private)
+            .flat_map(|rl| rl.idents.iter().map(|id| (id.to_owned(), rl.effective_pub)))
+            .chain(
+                self.imports
+                    .iter()
+                    .filter(|imp| imp.is_pub)
+                    .filter_map(|imp| imp.name().map(|s| (s.to_string(), !self.is_private(s)))),
+            )
+            .collect()
+    }
+
+    /// The doc comment lines of this file.
+    fn doc_lines(&self) -> impl Iterator<Item = &RawLine> {
+        self.m
+            .values()
+            .filter(|rl| rl.flavour == Flavour::RUST_DOCS)
+    }
+
+    /// Is the item `ident` refers to here private? External names are never private.
+    fn is_private(&self, ident: &str) -> bool {
+        self.private_idents.contains(ident)
+    }
+
+    /// Applies each of the `rules` in turn to each [`DocBlock`], `idents` being what can be
+    /// linked and `symbols` the tree's symbol table.
+    pub fn make_adjustments(
+        &self,
+        idents: &[String],
+        symbols: &[Symbol],
+        rules: &[Box<dyn Rule>],
+        cfg: &Config,
+    ) -> Vec<AdjustedLine> {
+        let mut adjusted = Vec::new();
+
+        for block in self.doc_blocks.iter() {
+            let mut lines = block
+                .lines
+                .iter()
+                .filter_map(|n| self.m.get(n))
+                .map(|rl| (rl.line_num, rl.to_owned()))
+                .collect::<BTreeMap<usize, RawLine>>();
+            let mut edits = Vec::new();
+
+            for rule in rules {
+                let ctx = RuleContext {
+                    block,
+                    lines: &lines,
+                    source: self,
+                    idents,
+                    symbols,
+                    cfg,
+                };
+                let mut proposed = rule.apply(&ctx);
+                // Last first so the ranges of those before stay put.
+                proposed.sort_by_key(|e| (e.line_num, e.range.start));
+                let mut made = Vec::new();
+                let mut free_until: Option<(usize, usize)> = None;
+                for edit in proposed.into_iter().rev() {
+                    if matches!(free_until, Some((n, start)) if n == edit.line_num && edit.range.end > start)
+                    {
+                        debug!("{} overlaps another edit, dropped: {:?}", rule.id(), edit);
+                        continue;
+                    }
+                    let Some(rl) = lines.get_mut(&edit.line_num) else {
+                        continue;
+                    };
+                    if let Some(contents) = edit.apply(&rl.contents) {
+                        rl.contents = contents;
+                        free_until = Some((edit.line_num, edit.range.start));
+                        made.push(edit);
+                    }
+                }
+                edits.extend(made.into_iter().rev());
+            }
+
+            for (n, rl) in lines {
+                if self.m.get(&n).map(|orig| &orig.contents) != Some(&rl.contents) {
+                    adjusted.push(AdjustedLine {
+                        line_num: n,
+                        contents: rl.contents,
+                        source_file: rl.source_file,
+                        edits: edits.iter().filter(|e| e.line_num == n).cloned().collect(),
+                    });
+                }
+            }
+        }
+        adjusted
     }
 }
 
 #[derive(Debug, Default)]
 pub struct ReportCard {
+    pub crate_name: Option<String>,
     pub source_files: Vec<RawSourceCode>,
+    pub symbols: Vec<Symbol>,
     pub named_idents: Vec<String>,
     pub num_funcs: usize,
     pub num_pub_funcs: usize,
@@ -230,69 +1171,151 @@ pub struct ReportCard {
     pub num_traits: usize,
     pub num_pub_traits: usize,
 
+    pub num_consts: usize,
+    pub num_pub_consts: usize,
+
+    pub num_statics: usize,
+    pub num_pub_statics: usize,
+
+    pub num_unions: usize,
+    pub num_pub_unions: usize,
+
+    pub num_mods: usize,
+    pub num_pub_mods: usize,
+
     pub num_macros: usize,
+    pub num_impls: usize,
 }
 
 impl ReportCard {
     pub fn from_source_tree(st: SourceTree) -> Self {
         let mut rc = ReportCard::default();
-        st.source_files
-            .iter()
-            .map(|rsc| rc.process(rsc))
-            .collect::<()>();
+        st.source_files.iter().for_each(|rsc| rc.process(rsc));
 
         rc.source_files = st.source_files;
+        rc.symbols = st.symbols;
+        rc.crate_name = st.crate_name;
         rc
     }
 
     pub fn process(&mut self, rsc: &RawSourceCode) {
-        rsc.iter()
-            .flat_map(|(_k, v)| v.report(self))
-            .collect::<()>();
+        rsc.values().for_each(|v| v.report(self));
     }
 
     //TODO: DRY this up...
     pub fn pretty_print(&self) {
-        println!("REPORT:");
+        match &self.crate_name {
+            Some(name) => println!("REPORT ({}):", name),
+            None => println!("REPORT:"),
+        }
         println!(" fns    : {}", self.num_funcs + self.num_pub_funcs);
         println!(" structs: {}", self.num_structs + self.num_pub_structs);
         println!(" enums  : {}", self.num_enums + self.num_pub_enums);
         println!(" types  : {}", self.num_types + self.num_pub_types);
         println!(" traits : {}", self.num_traits + self.num_pub_traits);
+        println!(" consts : {}", self.num_consts + self.num_pub_consts);
+        println!(" statics: {}", self.num_statics + self.num_pub_statics);
+        println!(" unions : {}", self.num_unions + self.num_pub_unions);
+        println!(" mods   : {}", self.num_mods + self.num_pub_mods);
+        println!(" macros : {}", self.num_macros);
+        println!(" impls  : {}", self.num_impls);
 
         //TODO: % of things that're public.
         //println!("% public:\n");
+
+        let mut public = self
+            .symbols
+            .iter()
+            .filter(|sym| sym.is_public())
+            .collect::<Vec<&Symbol>>();
+        public.sort_by_key(|sym| sym.canonical().cloned());
+
+        let root = self
+            .crate_name
+            .as_ref()
+            .map(|name| name.replace('-', "_"))
+            .unwrap_or_else(|| "crate".into());
+        println!("\nPUBLIC ITEMS:");
+        for sym in public {
+            let paths = sym
+                .public_paths
+                .iter()
+                .map(|p| display_path(&root, p))
+                .collect::<Vec<String>>();
+            println!(" {:<24} {}", sym.ident, paths.join(", "));
+        }
     }
 }
 
 impl RawLine {
     /// Will return true for a SINGLE instance of when a modification should be made, how many may
     /// really be in there is the domain of [`process`]
-    fn should_be_modified(&self, idents: &[String]) -> bool {
-        // NOTE: this isn't as bad as you'd initially think, you're out at the first branch if it's
-        // not a docstring, or, out at the first 'hit'.
-        if matches!(self.flavour, Flavour::RUST_DOCS) {
-            for i in idents {
-                if self.contents.contains(i)
-                    || self.contents.contains(&format!("{}s", i))
-                    || self.contents.contains(&format!("{}.", i))
-                    || self.contents.contains(&format!("{}'s", i)) && self.contents.contains("///")
-                    || self.contents.contains("//!")
-                {
-                    return true;
-                }
-            }
+    pub(crate) fn should_be_modified(&self, idents: &[String]) -> bool {
+        if !matches!(self.flavour, Flavour::RUST_DOCS) {
+            return false;
         }
-        false
+        // Every ident a path in the line starts with, plurals included as they're linked too.
+        let words = RUST_PROSE_PATH
+            .find_iter(&self.contents)
+            .map(|m| m.as_str().split("::").next().unwrap_or_default())
+            .collect::<HashSet<&str>>();
+        idents.iter().any(|i| {
+            // A dependency's item by its crate-qualified path, i.e `anyhow::Result`.
+            let i = i
+                .trim_end_matches('!')
+                .split("::")
+                .next()
+                .unwrap_or_default();
+            words.contains(i) || words.contains(format!("{}s", i).as_str())
+        })
     }
 
     /// Used by the report functionality.
     fn pub_or_private(&self) -> bool {
-        self.contents.contains("pub")
+        self.effective_pub
+    }
+
+    /// Declared as anything but `pub`, `#[macro_export]`ed macros are public despite not being.
+    fn is_private(&self) -> bool {
+        let exported = self.flavour == Flavour::RUST_MACRO
+            && self.attrs.iter().any(|a| a.contains("macro_export"));
+        !self.visibility.is_public() && !exported
+    }
+
+    /// Does this line declare an item (or `use` something), i.e could attributes apply to it?
+    fn is_item(&self) -> bool {
+        !matches!(
+            self.flavour,
+            Flavour::RUST_DOCS | Flavour::RUST_IMPORT | Flavour::Tasteless
+        )
+    }
+
+    /// Declares something, an item or an enum's variant.
+    fn declares(&self) -> bool {
+        self.is_item() || self.member.is_some()
+    }
+
+    /// The names of what's declared on this line and the paths they're declared at, `module`
+    /// being the module the line is in. Associated items and variants are under what they belong
+    /// to, i.e `module::Thing::new`.
+    fn declared_paths(&self, module: &[String]) -> Vec<(String, Vec<String>)> {
+        let names = match &self.member {
+            Some(member) => vec![member.clone()],
+            None => self.idents.clone(),
+        };
+        names
+            .into_iter()
+            .map(|ident| {
+                let mut path = module.to_vec();
+                path.extend(self.owner.iter().cloned());
+                path.push(ident.trim_end_matches('!').to_string());
+                (ident, path)
+            })
+            .collect()
     }
     /// WIP!
     /// Produce a report on the source at hand..
-    fn report(&self, rc: &mut ReportCard) -> Result<()> {
+    fn report(&self, rc: &mut ReportCard) {
         match self.flavour {
             Flavour::RUST_FN => {
                 if self.pub_or_private() {
@@ -318,7 +1341,7 @@ impl RawLine {
             }
             Flavour::RUST_TRAIT => {
                 if self.pub_or_private() {
-                    rc.num_pub_types += 1
+                    rc.num_pub_traits += 1
                 } else {
                     rc.num_traits += 1
                 }
@@ -330,45 +1353,209 @@ impl RawLine {
                     rc.num_structs += 1
                 }
             }
+            Flavour::RUST_CONST => {
+                if self.pub_or_private() {
+                    rc.num_pub_consts += 1
+                } else {
+                    rc.num_consts += 1
+                }
+            }
+            Flavour::RUST_STATIC => {
+                if self.pub_or_private() {
+                    rc.num_pub_statics += 1
+                } else {
+                    rc.num_statics += 1
+                }
+            }
+            Flavour::RUST_UNION => {
+                if self.pub_or_private() {
+                    rc.num_pub_unions += 1
+                } else {
+                    rc.num_unions += 1
+                }
+            }
+            Flavour::RUST_MOD => {
+                if self.pub_or_private() {
+                    rc.num_pub_mods += 1
+                } else {
+                    rc.num_mods += 1
+                }
+            }
+            Flavour::RUST_MACRO => rc.num_macros += 1,
+            Flavour::RUST_IMPL => rc.num_impls += 1,
             _ => (),
         }
-
-        Ok(())
     }
-    /// Actually [`process`] the modifications to a [`RawLine`]'s contents.
-    fn process_changes(mut self, idents: &[String]) -> Self {
+    /// An empty doc line, i.e the break between two paragraphs.
+    pub(crate) fn is_blank_doc(&self) -> bool {
+        matches!(self.contents.trim(), "///" | "//!")
+    }
+
+    /// Actually [`process`] the modifications to a [`RawLine`]'s contents, `block` being the
+    /// [`DocBlock`] the line is from and `seen` what's already been linked in it. The edits are
+    /// to the line as it is, its spacing left alone, and are the [`Rule`]'s to fill its id in.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn process_changes(
+        &self,
+        idents: &[String],
+        targets: &HashMap<String, LinkTarget>,
+        symbols: &[Symbol],
+        block: &DocBlock,
+        cfg: &Config,
+        seen: &mut HashSet<String>,
+        private: &HashSet<String>,
+        usage: &WordUsage,
+    ) -> Vec<SpanEdit> {
+        // Existing links, autolinks and code spans are left be.
+        let protected = protected_ranges(&self.contents, true);
+        let words = words_of(&self.contents);
+        let mut edits: Vec<SpanEdit> = Vec::new();
+
         for id in idents {
-            // TODO: how to not capture this ';' in the first place?
-            self.contents = self.contents.replace(";", "");
-            let split_n_proc = &self
-                .contents
-                .split_whitespace()
-                .map(|sp| {
-                    // Handle the always, i.e: i8 should always be `i8` etc...
-                    if ALWAYS.contains(&sp) {
-                        dbg!("ALWAYS");
-                        debug!("{} found always in: {}", id, sp);
-                        format!(" `{}`", id)
+            // Linking an item from its own docs is just noise.
+            if block.is_owner(id) && cfg.self_links == SelfLinks::Skip {
+                continue;
+            }
+            if private.contains(id) && cfg.private_links == PrivateLinks::Skip {
+                continue;
+            }
+            let backtick = (block.is_owner(id) && cfg.self_links == SelfLinks::Backtick)
+                || private.contains(id);
+            // Macros are stored with their '!' but are usually written without it in prose.
+            let bare = id.trim_end_matches('!');
+            // Already linked, so a repeat, see [`LinkPolicy`].
+            if protected
+                .iter()
+                .filter(|r| self.contents[r.start..].starts_with('['))
+                .flat_map(|r| RUST_PROSE_PATH.find_iter(&self.contents[r.clone()]))
+                .any(|m| split_head(m.as_str(), bare).0 == bare)
+            {
+                seen.insert(id.to_owned());
+            }
+
+            for (n, &(start, word)) in words.iter().enumerate() {
+                let before = n.checked_sub(1).and_then(|b| words.get(b)).map(|w| w.1);
+                let after = words.get(n + 1).map(|w| w.1);
+                for m in find_mentions(word, id) {
+                    let range = start + m.range.start..start + m.range.end;
+                    if protected
+                        .iter()
+                        .chain(edits.iter().map(|e| &e.range))
+                        .any(|r| overlaps(r, &range))
+                    {
+                        continue;
                     }
-                    // Handle the captures.
-                    else if sp.contains(id) && sp.len() > 3 {
-                        debug!("{} found cap in: {}", id, sp);
-                        format!(" [`{}`]", id)
+                    // `Thing::build()` is only a reference if there's a `build` on `Thing`.
+                    if !resolves_member(symbols, id, &m.suffix) {
+                        debug!("{} has nothing called {}", id, m.suffix);
+                        continue;
                     }
-                    // Unchanged...
-                    else {
-                        debug!("No change to: {} ", sp);
-                        format!(" {}", sp)
+                    let written = &word[m.range.clone()];
+                    let score = confidence::score(bare, written, before, after, usage);
+                    if score < cfg.min_confidence {
+                        debug!("{} in {} scored {}, too low to link", id, word, score);
+                        continue;
                     }
-                    //
-                })
-                .collect::<String>();
-            self.contents = split_n_proc.to_string();
+
+                    let replacement = if backtick {
+                        debug!("{} found self or private reference in: {}", id, word);
+                        format!("`{}`", m.text)
+                    }
+                    // Handle the repeats, see [`LinkPolicy`].
+                    else if cfg.link_policy != LinkPolicy::All && seen.contains(id) {
+                        debug!("{} already linked, repeated in: {}", id, word);
+                        match cfg.backtick_repeats {
+                            true => format!("`{}`", m.text),
+                            false => continue,
+                        }
+                    } else {
+                        debug!("{} found cap in: {}", id, word);
+                        seen.insert(id.to_owned());
+                        match targets.get(id) {
+                            Some(target) => target
+                                .extend(&m.suffix)
+                                .unwrap_or_else(|| target.clone())
+                                .link(&m.text),
+                            None => format!("[`{}`]", m.text),
+                        }
+                    };
+                    edits.push(SpanEdit {
+                        line_num: self.line_num,
+                        range,
+                        replacement,
+                        rule: String::new(),
+                        message: format!("{} {}%", m.text, (score * 100.0) as u8),
+                    });
+                }
+            }
         }
-        self
+        edits
     }
+
+    /// Upgrades code spans naming an item exactly to links, i.e `Foo`, `Foo::bar` and `foo()`,
+    /// rust-doc resolves the rest of a path so long as its first segment is in scope. The edits
+    /// are the [`Rule`]'s to fill its id in, as with [`RawLine::process_changes`].
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn link_code_spans(
+        &self,
+        idents: &[String],
+        targets: &HashMap<String, LinkTarget>,
+        symbols: &[Symbol],
+        block: &DocBlock,
+        cfg: &Config,
+        seen: &mut HashSet<String>,
+        private: &HashSet<String>,
+    ) -> Vec<SpanEdit> {
+        // Code spans that are (part of) the text of a link already.
+        let links = protected_ranges(&self.contents, false);
+        let mut edits = Vec::new();
+
+        for caps in RUST_CODE_SPAN.captures_iter(&self.contents) {
+            let (Some(whole), Some(code)) = (caps.get(0), caps.name("code")) else {
+                continue;
+            };
+            if !caps["open"].is_empty() || links.iter().any(|r| overlaps(r, &whole.range())) {
+                continue;
+            }
+            let code = code.as_str();
+            let Some((head, rest)) = split_item_path(code) else {
+                continue;
+            };
+            let Some(id) = idents.iter().find(|id| *id == head) else {
+                continue;
+            };
+            if !resolves_member(symbols, head, &rest) {
+                debug!("{} has nothing called {}", head, rest);
+                continue;
+            }
+            if block.is_owner(id)
+                || private.contains(id)
+                || (cfg.link_policy != LinkPolicy::All && seen.contains(id))
+            {
+                continue;
+            }
+            let link = match targets.get(id) {
+                Some(target) => match target.extend(&rest) {
+                    Some(target) => target.link(code),
+                    None => continue,
+                },
+                None => format!("[`{}`]", code),
+            };
+            debug!("{} found in code span: {}", id, code);
+            seen.insert(id.to_owned());
+            edits.push(SpanEdit {
+                line_num: self.line_num,
+                range: whole.start()..whole.end() - caps["close"].len(),
+                replacement: link,
+                rule: String::new(),
+                message: format!("linked `{}`", code),
+            });
+        }
+        edits
+    }
+
     /// Finds the things we're interested in.
-    fn find_idents(&mut self) {
+    pub(crate) fn find_idents(&mut self) {
         let text = self.contents.to_owned();
 
         // DRYness, is goodness.
@@ -380,7 +1567,7 @@ impl RawLine {
                         //TODO: You've got more flavours, use them...
                         self.flavour = Flavour::$CONST;
                         if cap != "" && !NEVERS.iter().any(|c| c == &cap) && cap.len() > 2{
-                            self.idents.push(cap.clone());
+                            self.idents.push(self.flavour.linkable(cap));
 
                             // DEBUG CAPTURES LINE-BY-LINE
                             //eprintln!("{}", "-".repeat(40));
@@ -404,25 +1591,287 @@ impl RawLine {
             RUST_TY,
             RUST_ENUM,
             RUST_STRUCT,
-            RUST_IMPORT,
-            RUST_USE,
-            RUST_TRAIT
+            RUST_TRAIT,
+            RUST_MACRO,
+            RUST_CONST,
+            RUST_STATIC,
+            RUST_UNION,
+            RUST_MOD
         );
+
+        // Impls don't introduce a name of their own, so they're classified but never linked.
+        if let Some(caps) = RUST_IMPL.captures(&text) {
+            self.flavour = Flavour::RUST_IMPL;
+            if let Some(v) = caps.name("ident") {
+                debug!("impl found for: {}", v.as_str());
+            }
+        }
     }
 
     /// Find and classify docstrings.
     // NOTE: also regex controlled, see ./src/search/consts.rs
-    // TODO: add support for '//!' module docstrings.
-    fn find_docs(&mut self) {
+    pub(crate) fn find_docs(&mut self) {
         let text = self.contents.to_owned();
         for caps in RUST_DOCSTRING.captures_iter(&text) {
-            if let Some(_) = caps.name("ident") {
+            if caps.name("ident").is_some() {
                 self.flavour = Flavour::RUST_DOCS;
             }
         }
     }
 }
 
+/// A reference to an item in a word of prose, i.e `Foo::bar()` in "call Foo::bar(), then".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Mention {
+    /// Where in the word it is, punctuation either side isn't part of it.
+    pub range: std::ops::Range<usize>,
+    /// The reference as it's linked, i.e `Foo::bar()`, `Vec<Foo>` or `my_macro!`.
+    pub text: String,
+    /// What it adds to the path of the item itself, i.e `::bar()`.
+    pub suffix: String,
+}
+
+/// The references to the item `id` in the whitespace free `word`, qualified paths, calls,
+/// generics, possessives (`Foo's`) and plurals (`Foos`) included.
+pub(crate) fn find_mentions(word: &str, id: &str) -> Vec<Mention> {
+    let bare = id.trim_end_matches('!');
+    let mut mentions = Vec::new();
+
+    for m in RUST_PROSE_PATH.find_iter(word) {
+        // Inside an existing link or code span.
+        if word[..m.start()].matches('`').count() % 2 == 1 {
+            continue;
+        }
+        let path = m.as_str();
+        let (head, members) = split_head(path, bare);
+
+        if members.is_empty() && head == format!("{}s", bare) {
+            mentions.push(Mention {
+                range: m.start()..m.start() + bare.len(),
+                text: id.to_string(),
+                suffix: String::new(),
+            });
+            continue;
+        }
+        if head != bare {
+            continue;
+        }
+
+        let mut end = m.end();
+        let mut text = match members.is_empty() {
+            true => id.to_string(),
+            false => path.to_string(),
+        };
+        if id.ends_with('!') && word[end..].starts_with('!') {
+            end += 1;
+        }
+        if let Some(len) = generics_len(&word[end..]) {
+            text.push_str(&word[end..end + len]);
+            end += len;
+        }
+        let mut suffix = members;
+        if word[end..].starts_with("()") {
+            text.push_str("()");
+            suffix.push_str("()");
+            end += 2;
+        }
+        mentions.push(Mention {
+            range: m.start()..end,
+            text,
+            suffix,
+        });
+    }
+    mentions
+}
+
+/// Splits `path` into as many segments as `id` has and the rest, i.e `anyhow::Result::ok` into
+/// `anyhow::Result` and `::ok` for the crate-qualified `anyhow::Result` of a dependency.
+fn split_head<'a>(path: &'a str, id: &str) -> (&'a str, String) {
+    let depth = id.matches("::").count() + 1;
+    match path.match_indices("::").nth(depth - 1) {
+        Some((i, _)) => (&path[..i], path[i..].to_string()),
+        None => (path, String::new()),
+    }
+}
+
+/// The length of the `<..>` generics `rest` starts with, if it does.
+fn generics_len(rest: &str) -> Option<usize> {
+    if !rest.starts_with('<') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            '`' | '[' | ']' => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The whitespace separated words of `text`, each with where it starts.
+fn words_of(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                words.push((s, &text[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, &text[s..]));
+    }
+    words
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Splits a mention of an item, i.e `Foo::bar()`, into the item it starts with and the rest of it:
+/// `("Foo", "::bar()")`, `None` when it isn't a path at all.
+pub fn split_item_path(code: &str) -> Option<(&str, String)> {
+    let (path, call) = match code.strip_suffix("()") {
+        Some(path) => (path, "()"),
+        None => (code, ""),
+    };
+    let (head, rest) = match path.split_once("::") {
+        Some((head, rest)) => (head, format!("::{}", rest)),
+        None => (path, String::new()),
+    };
+    let is_word = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !is_word(head.trim_end_matches('!')) || !rest.split("::").skip(1).all(is_word) {
+        return None;
+    }
+    Some((head, format!("{}{}", rest, call)))
+}
+
+/// How much deeper in `{}` a line leaves us, string literals (which may span lines, hence
+/// `in_str`), char literals and comments are ignored.
+fn brace_delta(text: &str, in_str: &mut bool) -> isize {
+    let mut delta = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if *in_str => {
+                chars.next();
+            }
+            '"' => *in_str = !*in_str,
+            _ if *in_str => (),
+            '/' if chars.peek() == Some(&'/') => break,
+            '\'' => {
+                // '{' and '\u{..}' but not lifetimes like 'a
+                let rest = chars.clone().take(12).collect::<String>();
+                if let Some(end) = rest.find('\'') {
+                    if end == 1 || rest.starts_with('\\') {
+                        (0..=end).for_each(|_| {
+                            chars.next();
+                        });
+                    }
+                }
+            }
+            '{' => delta += 1,
+            '}' => delta -= 1,
+            _ => (),
+        }
+    }
+    delta
+}
+
+/// Works out which module a file is from its path, relative to the nearest `src` directory.
+fn module_path_of(file: &Path) -> Vec<String> {
+    let components = file
+        .iter()
+        .map(|c| c.to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    let start = components
+        .iter()
+        .rposition(|c| c == "src")
+        .map(|idx| idx + 1)
+        .unwrap_or(components.len().saturating_sub(1));
+
+    let mut path = components[start..].to_vec();
+    if let Some(last) = path.pop() {
+        let stem = last.trim_end_matches(".rs");
+        if !matches!(stem, "lib" | "main" | "mod") {
+            path.push(stem.to_string());
+        }
+    }
+    path
+}
+
+/// Every `.rs` file under `dir`, build output excluded.
+pub fn find_source_files<P: Display>(dir: P) -> Vec<PathBuf> {
+    glob(&format!("{}/**/*.rs", dir))
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|f| !f.display().to_string().contains("target/"))
+        .collect()
+}
+
+/// Follows `pub use` re-exports from `path` back to where the item is actually declared.
+fn chase<T>(path: &[String], aliases: &HashMap<Vec<String>, (Vec<String>, T)>) -> Vec<String> {
+    let mut path = path.to_vec();
+    // Bounded, as `pub use` cycles are a compile error but we may be looking at broken code.
+    for _ in 0..16 {
+        match aliases.get(&path) {
+            Some((target, _)) if *target != path => path = target.clone(),
+            _ => break,
+        }
+    }
+    path
+}
+
+/// Resolves the module a `use` path points at, relative to the `current` module, only modules
+/// found in `known` will resolve.
+fn resolve_module<T>(
+    current: &[String],
+    path: &[String],
+    known: &HashMap<Vec<String>, T>,
+) -> Option<Vec<String>> {
+    let mut module = current.to_vec();
+    let mut rest = path;
+
+    match path.first().map(|s| s.as_str()) {
+        Some("crate") => {
+            module.clear();
+            rest = &path[1..];
+        }
+        Some("self") => rest = &path[1..],
+        Some("super") => {
+            while rest.first().map(|s| s == "super").unwrap_or(false) {
+                module.pop();
+                rest = &rest[1..];
+            }
+        }
+        _ => {
+            // 2018 style paths may start from a child module, or from the crate root.
+            let mut child = module.clone();
+            child.extend(rest.iter().cloned());
+            if known.contains_key(&child) {
+                return Some(child);
+            }
+            module.clear();
+        }
+    }
+
+    module.extend(rest.iter().cloned());
+    known.contains_key(&module).then_some(module)
+}
+
 // Boilerplates....
 impl Display for AdjustedLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -449,6 +1898,452 @@ impl DerefMut for RawSourceCode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::{config::Mentions, rules::builtin_rules, test_utils::TestDir};
+
+    #[test]
+    fn finds_new_flavours() {
+        let cases = [
+            (
+                "macro_rules! my_macro {",
+                Flavour::RUST_MACRO,
+                Some("my_macro!"),
+            ),
+            (
+                "pub const MAX_DEPTH: usize = 3;",
+                Flavour::RUST_CONST,
+                Some("MAX_DEPTH"),
+            ),
+            (
+                "static mut COUNTER: u32 = 0;",
+                Flavour::RUST_STATIC,
+                Some("COUNTER"),
+            ),
+            (
+                "pub(crate) union IntOrFloat {",
+                Flavour::RUST_UNION,
+                Some("IntOrFloat"),
+            ),
+            ("pub mod search;", Flavour::RUST_MOD, Some("search")),
+            (
+                "pub const fn frobnicate() {}",
+                Flavour::RUST_FN,
+                Some("frobnicate"),
+            ),
+            (
+                "impl<T: Into<String>> Display for Wrapper<T> {",
+                Flavour::RUST_IMPL,
+                None,
+            ),
+        ];
+
+        for (text, flavour, ident) in cases {
+            let mut rl = RawLine {
+                contents: text.into(),
+                ..Default::default()
+            };
+            rl.find_idents();
+            assert_eq!(rl.flavour, flavour, "{}", text);
+            assert_eq!(rl.idents.first().map(|s| s.as_str()), ident, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_and_private_items_excluded() {
+        let rsc = RawSourceCode::new_from_contents(
+            "src/lib.rs",
+            &[
+                "pub fn adjust() {}",
+                "fn tidy() {}",
+                "#[test]",
+                "fn adjusts() {}",
+                "#[cfg(test)]",
+                "mod tests {",
+                "    pub fn fixture() {}",
+                "}",
+            ]
+            .join("\n"),
+        );
+
+        assert!(rsc.named_idents.contains(&"adjust".to_string()));
+        assert!(!rsc.named_idents.contains(&"adjusts".to_string()));
+        assert!(!rsc.named_idents.contains(&"fixture".to_string()));
+        assert!(rsc.private_idents.contains("tidy"));
+        assert!(!rsc.private_idents.contains("adjust"));
+    }
+
+    #[test]
+    fn effective_visibility() {
+        let dir = TestDir::new("effective_visibility");
+        dir.write(
+            "src/lib.rs",
+            "mod private;\npub use private::Exported;\npub(crate) fn publish() {}\n",
+        );
+        dir.write(
+            "src/private.rs",
+            "pub struct Exported;\npub struct Stranded;\n",
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let private = st
+            .source_files
+            .iter()
+            .flat_map(|sf| sf.private_idents.iter().cloned())
+            .collect::<HashSet<String>>();
+
+        assert!(private.contains("publish"));
+        assert!(private.contains("Stranded"));
+        assert!(!private.contains("Exported"));
+
+        let exported = st.symbols.iter().find(|s| s.ident == "Exported").unwrap();
+        assert_eq!(exported.path, vec!["private", "Exported"]);
+        assert_eq!(exported.link_target().as_deref(), Some("crate::Exported"));
+    }
+
+    #[test]
+    fn unsaved_contents_replace_the_file() {
+        let dir = TestDir::new("with_contents");
+        dir.write("src/lib.rs", "mod wire;\n");
+        let wire = dir.write("src/wire.rs", "pub struct Frame;\n");
+
+        let mut st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        st.add_targets(&HashMap::from([(
+            "Regex".to_string(),
+            LinkTarget::Qualified("regex::Regex".into()),
+        )]));
+        let edited = st.with_contents(&wire, "pub struct Frame;\npub struct Packet;\n");
+
+        assert_eq!(edited.source_files.len(), 2);
+        assert!(edited.symbols.iter().any(|s| s.ident == "Packet"));
+        assert!(!st.symbols.iter().any(|s| s.ident == "Packet"));
+        // What was linkable from outside of the tree still is.
+        assert!(edited
+            .source_files
+            .iter()
+            .all(|sf| sf.link_targets.contains_key("Regex")));
+    }
+
+    #[test]
+    fn self_links() {
+        let dir = TestDir::new("self_links");
+        dir.write(
+            "src/lib.rs",
+            "/// A Thing holds an Other, like any Thing.\npub struct Thing;\npub struct Other;\n",
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let fixed = |self_links| {
+            let cfg = Config {
+                self_links,
+                ..Default::default()
+            };
+            rsc.make_adjustments(&rsc.named_idents, &st.symbols, &builtin_rules(), &cfg)[0]
+                .contents
+                .clone()
+        };
+
+        assert_eq!(
+            fixed(SelfLinks::Skip),
+            "/// A Thing holds an [`Other`], like any Thing."
+        );
+        assert_eq!(
+            fixed(SelfLinks::Backtick),
+            "/// A `Thing` holds an [`Other`], like any `Thing`."
+        );
+    }
+
+    #[test]
+    fn link_policies() {
+        let dir = TestDir::new("link_policies");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "/// A Thing, then a Thing again.",
+                "///",
+                "/// Another paragraph, another Thing.",
+                "pub fn helper() {}",
+                "pub struct Thing;",
+            ]
+            .join("\n"),
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let fixed = |link_policy, backtick_repeats| {
+            let cfg = Config {
+                link_policy,
+                backtick_repeats,
+                ..Default::default()
+            };
+            rsc.make_adjustments(&rsc.named_idents, &st.symbols, &builtin_rules(), &cfg)
+                .into_iter()
+                .map(|adj| adj.contents)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            fixed(LinkPolicy::All, false),
+            vec![
+                "/// A [`Thing`], then a [`Thing`] again.",
+                "/// Another paragraph, another [`Thing`].",
+            ]
+        );
+        assert_eq!(
+            fixed(LinkPolicy::FirstPerBlock, false),
+            vec!["/// A [`Thing`], then a Thing again."]
+        );
+        assert_eq!(
+            fixed(LinkPolicy::FirstPerParagraph, false),
+            vec![
+                "/// A [`Thing`], then a Thing again.",
+                "/// Another paragraph, another [`Thing`].",
+            ]
+        );
+        assert_eq!(
+            fixed(LinkPolicy::FirstPerBlock, true),
+            vec![
+                "/// A [`Thing`], then a `Thing` again.",
+                "/// Another paragraph, another `Thing`.",
+            ]
+        );
+        assert_eq!(
+            fixed(LinkPolicy::FirstPerParagraph, true),
+            vec![
+                "/// A [`Thing`], then a `Thing` again.",
+                "/// Another paragraph, another [`Thing`].",
+            ]
+        );
+    }
+
+    #[test]
+    fn links_code_spans() {
+        let dir = TestDir::new("code_spans");
+        dir.write(
+            "src/lib.rs",
+            "/// Build a `Thing` with `Thing::new()`, or `helper()`, not `Other` or [`Thing`].\n\
+             /// Nor `Thing::make()`, there's no such thing, but `Mode::Fast` is.\n\
+             pub fn helper() {}\n\
+             pub struct Thing;\n\
+             impl Thing {\n    pub fn new() -> Self { Thing }\n}\n\
+             pub enum Mode {\n    Fast,\n}\n",
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let cfg = Config {
+            mentions: Mentions::Backticked,
+            ..Default::default()
+        };
+        let adjusted = rsc.make_adjustments(&rsc.named_idents, &st.symbols, &builtin_rules(), &cfg);
+
+        assert_eq!(
+            adjusted[0].contents,
+            "/// Build a [`Thing`] with [`Thing::new()`], or `helper()`, not `Other` or [`Thing`]."
+        );
+        assert_eq!(
+            adjusted[1].contents,
+            "/// Nor `Thing::make()`, there's no such thing, but [`Mode::Fast`] is."
+        );
+    }
+
+    #[test]
+    fn links_exactly_the_reference() {
+        let dir = TestDir::new("references");
+        dir.write(
+            "src/lib.rs",
+            &[
+                "/// Call Thing::make(), not Thing::build(), then pass the Things, Thing's parts or a",
+                "/// Vec<Thing> to finish().",
+                "pub fn helper() {}",
+                "pub struct Thing;",
+                "impl Thing {",
+                "    pub fn make() -> Self { Thing }",
+                "}",
+                "pub fn finish() {}",
+            ]
+            .join("\n"),
+        );
+
+        let st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        let rsc = &st.source_files[0];
+        let adjusted = rsc.make_adjustments(
+            &rsc.named_idents,
+            &st.symbols,
+            &builtin_rules(),
+            &Config::default(),
+        );
+
+        assert_eq!(
+            adjusted[0].contents,
+            "/// Call [`Thing::make()`], not Thing::build(), then pass the [`Thing`]s, [`Thing`]'s \
+             parts or a"
+        );
+        assert_eq!(adjusted[1].contents, "/// Vec<[`Thing`]> to [`finish()`].");
+    }
+
+    #[test]
+    fn no_partial_word_links() {
+        let idents = ["Tree", "run", "Item", "render!"].map(String::from);
+        let corpus = [
+            ("/// A SourceTree is a TreeNode, not a Tree_like.", None),
+            (
+                "/// Running, rerun and run_all() don't run anything.",
+                Some("/// Running, rerun and run_all() don't [`run`] anything."),
+            ),
+            (
+                "/// tree, TREE and RUN differ in case with Tree.",
+                Some("/// tree, TREE and RUN differ in case with [`Tree`]."),
+            ),
+            ("/// An éTree or Treeñ is some other word.", None),
+            (
+                "/// (Tree), Tree. and \"Tree\"",
+                Some("/// ([`Tree`]), [`Tree`]. and \"[`Tree`]\""),
+            ),
+            (
+                "/// ItemSet, Itemise and Items",
+                Some("/// ItemSet, Itemise and [`Item`]s"),
+            ),
+            (
+                "/// rendering via render!",
+                Some("/// rendering via [`render!`]"),
+            ),
+        ];
+
+        for (prose, expected) in corpus {
+            let rl = RawLine {
+                contents: prose.into(),
+                flavour: Flavour::RUST_DOCS,
+                ..Default::default()
+            };
+            let modified = rl.should_be_modified(&idents);
+            assert_eq!(modified, expected.is_some(), "{}", prose);
+            if let Some(expected) = expected {
+                let edits = rl.process_changes(
+                    &idents,
+                    &HashMap::new(),
+                    &[],
+                    &DocBlock::default(),
+                    &Config::default(),
+                    &mut HashSet::new(),
+                    &HashSet::new(),
+                    &WordUsage::default(),
+                );
+                let processed = edits
+                    .iter()
+                    .rev()
+                    .fold(prose.to_string(), |line, e| e.apply(&line).unwrap());
+                assert_eq!(processed, expected, "{}", prose);
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_existing_links_alone() {
+        let prose =
+            "///  A [Widget](struct.Widget.html), [the Widget type](https://example.com/Widget),\
+                     \t<https://example.com/Widget> or [Widget][w], not a `Widget`, but a Widget.";
+        let rl = RawLine {
+            contents: prose.into(),
+            flavour: Flavour::RUST_DOCS,
+            ..Default::default()
+        };
+        let edits = rl.process_changes(
+            &["Widget".to_string()],
+            &HashMap::new(),
+            &[],
+            &DocBlock::default(),
+            &Config::default(),
+            &mut HashSet::new(),
+            &HashSet::new(),
+            &WordUsage::default(),
+        );
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].apply(prose).unwrap(),
+            "///  A [Widget](struct.Widget.html), [the Widget type](https://example.com/Widget),\
+             \t<https://example.com/Widget> or [Widget][w], not a `Widget`, but a [`Widget`]."
+        );
+    }
+
+    #[test]
+    fn replacing_a_file_drops_what_it_lost() {
+        let dir = TestDir::new("replace_file");
+        dir.write("src/lib.rs", "pub mod docs;\npub mod items;\n");
+        let items = dir.write(
+            "src/items.rs",
+            "pub struct Gone;\npub struct Old;\npub struct Kept;\n",
+        );
+        dir.write(
+            "src/docs.rs",
+            "/// Uses Gone, Old and Kept.\npub fn use_them() {}\n",
+        );
+
+        let mut st = SourceTree::new_from_dir(dir.join("src").display().to_string());
+        // Old is renamed New, Gone is removed.
+        let rsc = RawSourceCode::new_from_contents(&items, "pub struct New;\npub struct Kept;\n");
+        let changed = st.replace_file(&items, Some(rsc));
+        let docs = st
+            .source_files
+            .iter()
+            .find(|sf| sf.module_path == ["docs"])
+            .unwrap();
+
+        assert_eq!(
+            changed,
+            ["Gone", "New", "Old"]
+                .map(String::from)
+                .into_iter()
+                .collect()
+        );
+        for lost in ["Gone", "Old"] {
+            assert_eq!(docs.link_targets.get(lost), None);
+            assert!(!docs.named_idents.contains(&lost.to_string()));
+            assert!(!st.named_idents.contains(&lost.to_string()));
+        }
+        assert_eq!(
+            docs.link_targets.get("New"),
+            Some(&LinkTarget::Explicit("crate::items::New".into()))
+        );
+        assert_eq!(
+            docs.link_targets.get("Kept"),
+            Some(&LinkTarget::Explicit("crate::items::Kept".into()))
+        );
+    }
+
+    #[test]
+    fn edits_of_the_original() {
+        let edit = |rule: &str, range: Range<usize>, replacement: &str| SpanEdit {
+            line_num: 0,
+            range,
+            replacement: replacement.into(),
+            rule: rule.into(),
+            message: rule.into(),
+        };
+        let adj = AdjustedLine {
+            line_num: 0,
+            contents: "/// a **`bool`** and a [`Foo`]".into(),
+            source_file: PathBuf::new(),
+            edits: vec![
+                edit("ticks", 6..10, "`bool`"),
+                edit("links", 19..22, "[`Foo`]"),
+                edit("bold", 6..12, "**`bool`**"),
+            ],
+        };
+
+        let edits = adj.original_edits("/// a bool and a Foo");
+        let made = edits
+            .iter()
+            .map(|e| (e.range.clone(), e.replacement.as_str(), e.rule.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            made,
+            vec![
+                (6..10, "**`bool`**", "ticks, bold"),
+                (17..20, "[`Foo`]", "links"),
+            ]
+        );
+    }
 
     #[test]
     fn trial_on_source() {
@@ -459,13 +2354,17 @@ mod tests {
         for rsc in st.source_files.iter() {
             debug!("{}", rsc.file.display());
             let new_m = rsc
-                .make_adjustments(&rsc.named_idents)
+                .make_adjustments(
+                    &rsc.named_idents,
+                    &st.symbols,
+                    &builtin_rules(),
+                    &Config::default(),
+                )
                 .into_iter()
                 .map(|adj| (adj.line_num, adj.contents))
                 .collect::<HashMap<usize, String>>();
 
             let output = (0..rsc.total_lines)
-                .into_iter()
                 .map(|n| -> String {
                     if let Some(new) = new_m.get(&n) {
                         new.to_owned()
@@ -0,0 +1,65 @@
+//!
+//! Item visibility, as declared and as it effectively is once module reachability and re-exports
+//! are accounted for.
+//!
+use super::consts::RUST_VIS;
+
+/// The visibility an item is declared with.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// `pub`
+    Public,
+    /// `pub(crate)`
+    Crate,
+    /// `pub(super)`
+    Super,
+    /// `pub(in some::path)` or `pub(self)`
+    Restricted(String),
+    /// No `pub` at all.
+    #[default]
+    Private,
+}
+
+impl Visibility {
+    /// Parses the visibility from the start of a line declaring an item, note that `publish` or
+    /// `pub` appearing anywhere else on the line doesn't count.
+    pub fn parse(line: &str) -> Self {
+        let caps = match RUST_VIS.captures(line) {
+            Some(caps) => caps,
+            None => return Visibility::Private,
+        };
+        match caps.name("scope").map(|s| s.as_str()) {
+            None => Visibility::Public,
+            Some("crate") => Visibility::Crate,
+            Some("super") => Visibility::Super,
+            Some("self") => Visibility::Private,
+            Some(path) => Visibility::Restricted(path.trim_start_matches("in ").to_string()),
+        }
+    }
+
+    /// Visible from outside the crate, assuming everything enclosing it is too.
+    pub fn is_public(&self) -> bool {
+        *self == Visibility::Public
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_restrictions() {
+        assert_eq!(Visibility::parse("pub struct Foo;"), Visibility::Public);
+        assert_eq!(
+            Visibility::parse("  pub(crate) fn foo()"),
+            Visibility::Crate
+        );
+        assert_eq!(Visibility::parse("pub(super) mod foo;"), Visibility::Super);
+        assert_eq!(
+            Visibility::parse("pub(in crate::search) fn foo()"),
+            Visibility::Restricted("crate::search".into())
+        );
+        assert_eq!(Visibility::parse("fn publish()"), Visibility::Private);
+        assert_eq!(Visibility::parse("struct Pub;"), Visibility::Private);
+    }
+}
@@ -0,0 +1,347 @@
+//!
+//! Cargo projects: which crates there are, where they are and which of each other they depend on.
+//!
+use super::{
+    config::Config,
+    deps::{Lockfile, Registry},
+    symbols::LinkTarget,
+    utils::{find_source_files, SourceTree},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize, Default, Debug)]
+struct Manifest {
+    package: Option<Package>,
+    workspace: Option<WorkspaceTable>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Package {
+    name: String,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct WorkspaceTable {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+impl Manifest {
+    fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join("Cargo.toml");
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+
+    /// The package names depended upon, mapped to the name they're known by in code, i.e
+    /// `my-dep` is `my_dep` and `renamed = { package = "my-dep" }` is `renamed`.
+    fn dependency_names(&self) -> HashMap<String, String> {
+        self.dependencies
+            .iter()
+            .map(|(key, value)| {
+                let package = value.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                (package.to_string(), key.replace('-', "_"))
+            })
+            .collect()
+    }
+}
+
+/// A crate of the workspace.
+#[derive(Debug)]
+pub struct Crate {
+    /// The package name, as it's written in `Cargo.toml`.
+    pub name: String,
+    pub root: PathBuf,
+    /// The package names of its dependencies, mapped to the name they're known by in code.
+    pub deps: HashMap<String, String>,
+    pub tree: SourceTree,
+}
+
+/// Every crate of a cargo project, be it a workspace or a lone package.
+#[derive(Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub crates: Vec<Crate>,
+}
+
+impl Workspace {
+    /// Reads the `Cargo.toml` in `dir` and every member's, building one [`SourceTree`] per crate.
+    pub fn from_dir(dir: &Path, cfg: &Config) -> Result<Self> {
+        Self::with_registry(dir, cfg, &mut Registry::from_env())
+    }
+
+    /// As [`Workspace::from_dir`], the sources of crates.io dependencies looked for in `registry`.
+    pub fn with_registry(dir: &Path, cfg: &Config, registry: &mut Registry) -> Result<Self> {
+        let manifest = Manifest::read(dir)?;
+        let mut members: Vec<(PathBuf, Manifest)> = Vec::new();
+
+        if let Some(ws) = &manifest.workspace {
+            let excluded = ws
+                .exclude
+                .iter()
+                .map(|e| dir.join(e))
+                .collect::<Vec<PathBuf>>();
+            for pattern in ws.members.iter() {
+                let pattern = dir.join(pattern);
+                for root in glob::glob(&pattern.display().to_string())?.filter_map(Result::ok) {
+                    if excluded.contains(&root) || !root.join("Cargo.toml").exists() {
+                        continue;
+                    }
+                    members.push((root.clone(), Manifest::read(&root)?));
+                }
+            }
+        }
+        if manifest.package.is_some() {
+            members.push((dir.to_path_buf(), manifest));
+        }
+
+        // Every file belongs to the crate with the deepest root containing it.
+        let mut files: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        for file in find_source_files(dir.display()) {
+            let owner = members
+                .iter()
+                .filter(|(root, _)| file.starts_with(root))
+                .max_by_key(|(root, _)| root.components().count());
+            if let Some((root, _)) = owner {
+                files.entry(root.clone()).or_default().push(file);
+            }
+        }
+
+        let mut crates = members
+            .into_iter()
+            .filter_map(|(root, manifest)| {
+                let name = manifest.package.as_ref()?.name.clone();
+                debug!("Found crate {} at {}", name, root.display());
+                let mut tree = SourceTree::new_from_files(files.get(&root).unwrap_or(&Vec::new()));
+                tree.crate_name = Some(name.clone());
+                tree.root = Some(root.clone());
+                Some(Crate {
+                    name,
+                    deps: manifest.dependency_names(),
+                    root,
+                    tree,
+                })
+            })
+            .collect::<Vec<Crate>>();
+
+        crates.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut ws = Workspace {
+            root: dir.to_path_buf(),
+            crates,
+        };
+        ws.link_dependencies();
+        ws.link_registry_dependencies(registry, cfg);
+        Ok(ws)
+    }
+
+    /// Public items of the crates a crate depends on are linkable from it, by their full path, be
+    /// they mentioned by name or by that path, i.e `b_dep::InB`.
+    fn link_dependencies(&mut self) {
+        let targets = self
+            .crates
+            .iter()
+            .map(|krate| {
+                let mut targets = HashMap::new();
+                for dep in self.crates.iter() {
+                    if let Some(known_as) = krate.deps.get(&dep.name) {
+                        targets.extend(dep.tree.public_targets(known_as, LinkTarget::Qualified));
+                        let public = dep.tree.symbols.iter().filter(|sym| !sym.associated);
+                        targets.extend(public.filter_map(|sym| {
+                            let path = sym.path_from(known_as)?;
+                            Some((path.clone(), LinkTarget::Qualified(path)))
+                        }));
+                    }
+                }
+                targets
+            })
+            .collect::<Vec<HashMap<String, LinkTarget>>>();
+
+        for (krate, targets) in self.crates.iter_mut().zip(targets) {
+            krate.tree.add_targets(&targets);
+        }
+    }
+
+    /// Public items of the crates.io crates a crate depends on are linkable from it too, provided
+    /// cargo has their sources locally.
+    fn link_registry_dependencies(&mut self, registry: &mut Registry, cfg: &Config) {
+        let lock = match Lockfile::read(&self.root) {
+            Ok(lock) => lock,
+            Err(e) => {
+                debug!("No lockfile, dependencies won't be linked, {:?}", e);
+                return;
+            }
+        };
+        for krate in self.crates.iter_mut() {
+            let targets = registry.targets(&lock, &krate.name, &krate.deps, cfg.dep_links);
+            krate.tree.add_targets(&targets);
+        }
+    }
+
+    pub fn trees(&self) -> impl Iterator<Item = &SourceTree> {
+        self.crates.iter().map(|c| &c.tree)
+    }
+}
+
+/// The trees to run over, one per crate when run from within a cargo project, or a single tree of
+/// whatever `paths`/the current directory holds otherwise.
+pub fn setup_trees(paths: &Option<Vec<String>>, cfg: &Config) -> Vec<SourceTree> {
+    if paths.is_some() {
+        return vec![SourceTree::setup_tree(paths)];
+    }
+    let cwd = std::env::current_dir().expect("Unable to ascertain current working directory, this is likely a permissions error with your OS.");
+
+    match Workspace::from_dir(&cwd, cfg) {
+        Ok(ws) => ws.crates.into_iter().map(|c| c.tree).collect(),
+        Err(e) => {
+            debug!("Not a cargo project, {:?}", e);
+            vec![SourceTree::new_from_cwd()]
+        }
+    }
+}
+
+/// Where the cargo project a file in `dir` belongs to is, the nearest directory at or above it with
+/// a `Cargo.toml`, or the root of the workspace that is a member of as cargo itself would have it.
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    let nearest = dir.ancestors().find(|d| d.join("Cargo.toml").exists())?;
+    let workspace = nearest.ancestors().find(|d| {
+        Manifest::read(d)
+            .map(|m| m.workspace.is_some())
+            .unwrap_or(false)
+    });
+    Some(workspace.unwrap_or(nearest).to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{rules::builtin_rules, std_index::std_targets, test_utils::TestDir};
+
+    #[test]
+    fn links_only_dependencies() {
+        let dir = TestDir::new("workspace");
+        dir.write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\"]\n",
+        );
+        dir.write(
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\n[dependencies]\nb-dep = { path = \"../b\", package = \"b\" }\n",
+        );
+        dir.write("b/Cargo.toml", "[package]\nname = \"b\"\n");
+        dir.write("c/Cargo.toml", "[package]\nname = \"c\"\n");
+        dir.write("a/src/lib.rs", "pub struct InA;\n");
+        dir.write("b/src/lib.rs", "pub struct InB;\n");
+        dir.write("c/src/lib.rs", "pub struct InC;\n");
+
+        let mut registry = Registry::new(dir.join("home"));
+        let ws = Workspace::with_registry(dir.path(), &Config::default(), &mut registry).unwrap();
+        let a = &ws.crates[0].tree.source_files[0];
+
+        assert_eq!(ws.crates.len(), 3);
+        assert_eq!(
+            a.link_targets.get("InB"),
+            Some(&LinkTarget::Qualified("b_dep::InB".into()))
+        );
+        assert!(!a.named_idents.contains(&"InC".to_string()));
+    }
+
+    #[test]
+    fn dependencies_win_over_std() {
+        let dir = TestDir::new("workspace_std");
+        dir.write(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n[dependencies]\nanyhow = \"1\"\n",
+        );
+        dir.write(
+            "Cargo.lock",
+            "[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"anyhow\"]\n\n\
+             [[package]]\nname = \"anyhow\"\nversion = \"1.0.0\"\n\
+             source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        let home = TestDir::new("workspace_std_home");
+        home.write(
+            "registry/src/index.crates.io-0/anyhow-1.0.0/src/lib.rs",
+            "pub struct Error;\npub type Result<T, E = Error> = core::result::Result<T, E>;\n",
+        );
+        dir.write(
+            "src/lib.rs",
+            "mod io;\n/// Returns a Result.\npub fn run() {}\n",
+        );
+        dir.write(
+            "src/io.rs",
+            "use std::io::Result;\n/// Returns a Result.\npub fn read() {}\n",
+        );
+
+        let mut registry = Registry::new(home.path().to_path_buf());
+        let mut ws =
+            Workspace::with_registry(dir.path(), &Config::default(), &mut registry).unwrap();
+        let tree = &mut ws.crates[0].tree;
+        tree.add_targets(&std_targets(false));
+        let file = |module: &str| {
+            tree.source_files
+                .iter()
+                .find(|sf| sf.module_path.join("::") == module)
+                .unwrap()
+        };
+
+        assert_eq!(
+            file("").link_targets.get("Result"),
+            Some(&LinkTarget::Qualified("anyhow::Result".into()))
+        );
+        // Imported, so in scope as it is.
+        assert!(file("io").named_idents.contains(&"Result".to_string()));
+        assert_eq!(file("io").link_targets.get("Result"), None);
+    }
+
+    #[test]
+    fn links_crate_qualified_paths() {
+        let dir = TestDir::new("workspace_qualified");
+        dir.write(
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n[dependencies]\nanyhow = \"1\"\n",
+        );
+        dir.write(
+            "Cargo.lock",
+            "[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"anyhow\"]\n\n\
+             [[package]]\nname = \"anyhow\"\nversion = \"1.0.0\"\n\
+             source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        let home = TestDir::new("workspace_qualified_home");
+        home.write(
+            "registry/src/index.crates.io-0/anyhow-1.0.0/src/lib.rs",
+            "pub struct Error;\npub type Result<T, E = Error> = core::result::Result<T, E>;\n",
+        );
+        dir.write(
+            "src/lib.rs",
+            "use std::io::Result;\n/// Returns a Result, not an anyhow::Result or anyhow::Nothing.\npub fn read() {}\n",
+        );
+
+        let mut registry = Registry::new(home.path().to_path_buf());
+        let ws = Workspace::with_registry(dir.path(), &Config::default(), &mut registry).unwrap();
+        let rsc = &ws.crates[0].tree.source_files[0];
+        let adjusted = rsc.make_adjustments(
+            &rsc.named_idents,
+            &ws.crates[0].tree.symbols,
+            &builtin_rules(),
+            &Config::default(),
+        );
+
+        assert_eq!(
+            adjusted[0].contents,
+            "/// Returns a [`Result`], not an [`anyhow::Result`] or anyhow::Nothing."
+        );
+    }
+}
@@ -15,7 +15,8 @@ pub fn setup_logger() -> Result<(), fern::InitError> {
             ))
         })
         .level(log::LevelFilter::Warn)
-        .chain(std::io::stdout())
+        // Not stdout, that's where `lsp`, `mdbook` and `fix --stdin` write what they're asked for.
+        .chain(std::io::stderr())
         .chain(fern::log_file(termite_path)?)
         .apply()?;
 